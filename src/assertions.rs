@@ -0,0 +1,85 @@
+//! Assertions on failed transactions with readable failure messages.
+
+use crate::error::TestFrameWorkError;
+use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+use std::fmt::Debug;
+
+/// Assert that a transaction failed in instruction `ix_index` with `InstructionError::Custom(error)`.
+#[track_caller]
+pub fn assert_custom_error<T: Debug, E: Into<TestFrameWorkError>>(
+    result: Result<T, E>,
+    ix_index: u8,
+    error: impl Into<u32>,
+) {
+    let code = error.into();
+    assert_instruction_error(result, ix_index, InstructionError::Custom(code), None);
+}
+
+/// Assert that a transaction failed in instruction `ix_index` with the given Anchor error,
+/// e.g. a variant of an `#[error_code]` enum. If the failure carries logs, the program must also
+/// have logged the matching `Error Code: .. Error Number: .. Error Message: ..` line.
+#[cfg(feature = "anchor")]
+#[track_caller]
+pub fn assert_anchor_error<T: Debug, E: Into<TestFrameWorkError>>(
+    result: Result<T, E>,
+    ix_index: u8,
+    error: impl Into<anchor_lang::error::Error>,
+) {
+    match error.into() {
+        anchor_lang::error::Error::AnchorError(error) => {
+            let log_line = format!(
+                "Error Code: {}. Error Number: {}. Error Message: {}.",
+                error.error_name, error.error_code_number, error.error_msg
+            );
+            assert_instruction_error(
+                result,
+                ix_index,
+                InstructionError::Custom(error.error_code_number),
+                Some(log_line),
+            );
+        }
+        anchor_lang::error::Error::ProgramError(error) => {
+            let expected = InstructionError::from(u64::from(error.program_error));
+            assert_instruction_error(result, ix_index, expected, None);
+        }
+    }
+}
+
+#[track_caller]
+fn assert_instruction_error<T: Debug, E: Into<TestFrameWorkError>>(
+    result: Result<T, E>,
+    ix_index: u8,
+    expected: InstructionError,
+    log_line: Option<String>,
+) {
+    let expected = TransactionError::InstructionError(ix_index, expected);
+
+    let (error, logs) = match result.map_err(Into::into) {
+        Ok(value) => panic!(
+            "expected transaction to fail with {:?}, but it succeeded with {:?}",
+            expected, value
+        ),
+        Err(TestFrameWorkError::Transaction { error, logs, .. }) => (error, logs),
+        Err(error) => panic!(
+            "expected transaction to fail with {:?}, but got {}",
+            expected, error
+        ),
+    };
+
+    assert!(
+        error == expected,
+        "expected transaction to fail with {:?}, but it failed with {:?}\nlogs:\n{}",
+        expected,
+        error,
+        logs.join("\n")
+    );
+
+    if let Some(log_line) = log_line {
+        assert!(
+            logs.is_empty() || logs.iter().any(|log| log.contains(&log_line)),
+            "expected the program to log \"{}\"\nlogs:\n{}",
+            log_line,
+            logs.join("\n")
+        );
+    }
+}
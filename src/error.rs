@@ -1,8 +1,120 @@
+use solana_banks_client::BanksClientError;
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_request::{RpcError, RpcResponseErrorData},
+};
+use solana_program::{
+    instruction::InstructionError, message::CompileError, program_error::ProgramError,
+};
+use solana_program_test::ProgramTestError;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Signature, SignerError},
+    transaction::TransactionError,
+};
 use thiserror::Error;
 
 /// Errors from the program test environment
-#[derive(Error, Debug, PartialEq, Eq)]
+#[derive(Error, Debug)]
 pub enum TestFrameWorkError {
     #[error("ProgramTestExtensionError: {0}")]
     Error(&'static str),
+    #[error("ProgramTestError: {0}")]
+    ProgramTest(#[from] ProgramTestError),
+    #[error("Account {0} not found")]
+    AccountNotFound(Pubkey),
+    #[error("Failed to deserialize account {pubkey} as {type_name}")]
+    Deserialize {
+        pubkey: Pubkey,
+        type_name: &'static str,
+    },
+    #[error("Transaction failed: {error}")]
+    Transaction {
+        error: TransactionError,
+        logs: Vec<String>,
+        /// Index of the failing instruction, if the error is an instruction error
+        ix_index: Option<u8>,
+    },
+    #[error("Blockhash of transaction {0} expired before it was confirmed")]
+    BlockhashExpired(Signature),
+    #[error("Transaction {0} was not confirmed in time")]
+    ConfirmationTimeout(Signature),
+    #[error("Instruction error: {0}")]
+    Instruction(#[from] InstructionError),
+    #[error("Program error: {0}")]
+    Program(#[from] ProgramError),
+    #[error("Signer error: {0}")]
+    Signer(#[from] SignerError),
+    #[error("Message compile error: {0}")]
+    Compile(#[from] CompileError),
+    #[error("Invalid program: {0}")]
+    InvalidProgram(String),
+    #[error("IDL error: {0}")]
+    Idl(String),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("BanksClient error: {0}")]
+    BanksClient(BanksClientError),
+    #[error("RPC error: {0}")]
+    Rpc(Box<ClientError>),
+}
+
+impl TestFrameWorkError {
+    /// Create a `Transaction` error, extracting the index of the failing instruction.
+    pub fn transaction(error: TransactionError, logs: Vec<String>) -> Self {
+        let ix_index = match error {
+            TransactionError::InstructionError(ix_index, _) => Some(ix_index),
+            _ => None,
+        };
+
+        Self::Transaction {
+            error,
+            logs,
+            ix_index,
+        }
+    }
+
+    /// Create a `Deserialize` error for an account that could not be read as `T`.
+    pub fn deserialize<T>(pubkey: Pubkey) -> Self {
+        Self::Deserialize {
+            pubkey,
+            type_name: std::any::type_name::<T>(),
+        }
+    }
+}
+
+impl From<TransactionError> for TestFrameWorkError {
+    fn from(error: TransactionError) -> Self {
+        Self::transaction(error, Vec::new())
+    }
+}
+
+impl From<BanksClientError> for TestFrameWorkError {
+    fn from(error: BanksClientError) -> Self {
+        match error {
+            BanksClientError::TransactionError(error) => Self::transaction(error, Vec::new()),
+            BanksClientError::SimulationError { err, logs, .. } => Self::transaction(err, logs),
+            BanksClientError::Io(error) => Self::Io(error),
+            error => Self::BanksClient(error),
+        }
+    }
+}
+
+impl From<ClientError> for TestFrameWorkError {
+    fn from(error: ClientError) -> Self {
+        match error.get_transaction_error() {
+            Some(transaction_error) => {
+                let logs = match &error.kind {
+                    ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                        data: RpcResponseErrorData::SendTransactionPreflightFailure(simulation),
+                        ..
+                    }) => simulation.logs.clone().unwrap_or_default(),
+                    _ => Vec::new(),
+                };
+
+                Self::transaction(transaction_error, logs)
+            }
+            None => Self::Rpc(Box::new(error)),
+        }
+    }
 }
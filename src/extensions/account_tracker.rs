@@ -0,0 +1,28 @@
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, Mutex},
+};
+
+/// Addresses of the accounts of one test.
+/// `BanksClient` cannot enumerate accounts, so `get_program_accounts` on a `TrackedBanksClient` scans every address
+/// that was seeded through its `TrackedProgramTest` or `TrackedContext`, or referenced by a transaction it processed.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AccountTracker(Arc<Mutex<BTreeSet<Pubkey>>>);
+
+impl AccountTracker {
+    /// Remember an address.
+    pub(crate) fn track(&self, address: Pubkey) {
+        self.0.lock().unwrap().insert(address);
+    }
+
+    /// Remember all given addresses.
+    pub(crate) fn track_all<'a>(&self, addresses: impl IntoIterator<Item = &'a Pubkey>) {
+        self.0.lock().unwrap().extend(addresses);
+    }
+
+    /// All addresses remembered so far, in ascending order.
+    pub(crate) fn addresses(&self) -> Vec<Pubkey> {
+        self.0.lock().unwrap().iter().copied().collect()
+    }
+}
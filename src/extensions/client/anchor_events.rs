@@ -0,0 +1,123 @@
+use super::{SimulationOutcome, TransactionOutcome};
+use crate::error::TestFrameWorkError;
+use anchor_lang::{event::EVENT_IX_TAG_LE, AnchorDeserialize, Event};
+use base64::{prelude::BASE64_STANDARD, Engine};
+use solana_sdk::{bs58, signature::Signature};
+use solana_transaction_status::{
+    option_serializer::OptionSerializer, EncodedConfirmedTransactionWithStatusMeta, UiInstruction,
+};
+
+const PROGRAM_DATA_LOG_PREFIX: &str = "Program data: ";
+
+/// Where `ClientExtensions::get_anchor_events` reads the emitted events from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventSource {
+    /// Fetch the logs and inner instructions of a confirmed transaction, only supported by `RpcClient`
+    Signature(Signature),
+    /// The logs of a processed or simulated transaction
+    Logs(Vec<String>),
+}
+
+impl From<Signature> for EventSource {
+    fn from(signature: Signature) -> Self {
+        Self::Signature(signature)
+    }
+}
+
+impl From<&TransactionOutcome> for EventSource {
+    fn from(outcome: &TransactionOutcome) -> Self {
+        Self::Logs(outcome.logs.clone())
+    }
+}
+
+impl From<&SimulationOutcome> for EventSource {
+    fn from(outcome: &SimulationOutcome) -> Self {
+        Self::Logs(outcome.logs.clone())
+    }
+}
+
+impl From<&[String]> for EventSource {
+    fn from(logs: &[String]) -> Self {
+        Self::Logs(logs.to_vec())
+    }
+}
+
+/// Decode the events of type `T` emitted with `emit!` from `Program data:` log lines.
+pub fn parse_anchor_events<T: Event>(logs: &[String]) -> Result<Vec<T>, TestFrameWorkError> {
+    let mut events = Vec::new();
+
+    for data in logs
+        .iter()
+        .filter_map(|log| log.strip_prefix(PROGRAM_DATA_LOG_PREFIX))
+    {
+        // other programs may log data that is not base64 encoded
+        if let Ok(data) = BASE64_STANDARD.decode(data) {
+            if let Some(event) = decode_event(&data)? {
+                events.push(event);
+            }
+        }
+    }
+
+    Ok(events)
+}
+
+/// Decode the events of type `T` emitted with `emit_cpi!` from the data of self-CPI inner instructions.
+pub fn parse_anchor_cpi_events<T: Event>(
+    inner_instructions_data: &[Vec<u8>],
+) -> Result<Vec<T>, TestFrameWorkError> {
+    let mut events = Vec::new();
+
+    for data in inner_instructions_data {
+        if let Some(data) = data.strip_prefix(EVENT_IX_TAG_LE.as_slice()) {
+            if let Some(event) = decode_event(data)? {
+                events.push(event);
+            }
+        }
+    }
+
+    Ok(events)
+}
+
+/// Decode the events of type `T` emitted by a confirmed transaction, `emit!` events first.
+pub(crate) fn events_from_confirmed_transaction<T: Event>(
+    transaction: EncodedConfirmedTransactionWithStatusMeta,
+) -> Result<Vec<T>, TestFrameWorkError> {
+    let meta = transaction
+        .transaction
+        .meta
+        .ok_or(TestFrameWorkError::Error(
+            "Transaction status meta not available",
+        ))?;
+
+    let logs = match meta.log_messages {
+        OptionSerializer::Some(logs) => logs,
+        _ => Vec::new(),
+    };
+    let inner_instructions_data: Vec<Vec<u8>> = match meta.inner_instructions {
+        OptionSerializer::Some(inner_instructions) => inner_instructions
+            .into_iter()
+            .flat_map(|inner_instructions| inner_instructions.instructions)
+            .filter_map(|instruction| match instruction {
+                UiInstruction::Compiled(instruction) => {
+                    bs58::decode(instruction.data).into_vec().ok()
+                }
+                UiInstruction::Parsed(_) => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let mut events = parse_anchor_events(&logs)?;
+    events.extend(parse_anchor_cpi_events(&inner_instructions_data)?);
+    Ok(events)
+}
+
+/// Deserialize an event of type `T` if the data starts with its discriminator.
+fn decode_event<T: Event>(data: &[u8]) -> Result<Option<T>, TestFrameWorkError> {
+    match data.strip_prefix(T::DISCRIMINATOR.as_slice()) {
+        Some(mut event_data) => AnchorDeserialize::deserialize(&mut event_data)
+            .map(Some)
+            .map_err(|_| TestFrameWorkError::Error("Failed to deserialize anchor event")),
+        None => Ok(None),
+    }
+}
@@ -1,64 +1,377 @@
 use super::*;
+use crate::extensions::{
+    test_state::TestState,
+    transaction_hooks::{
+        process_transaction_with_hooks, process_transactions_with_hooks,
+        track_transaction_accounts, TransactionHook,
+    },
+};
+use solana_banks_interface::BanksTransactionResultWithMetadata;
+use std::ops::{Deref, DerefMut};
 
 #[cfg(feature = "pyth")]
 use pyth_sdk_solana::state::PriceAccount;
 
+/// A `BanksClient` of a `TrackedProgramTest`, see `TrackedProgramTest::start`.
+/// It records the accounts referenced by the transactions it processes, including those sent with the
+/// `BanksClient` methods it dereferences to, so `get_program_accounts` finds them,
+/// and runs the transaction hooks of its test around the transactions of `ClientExtensions`.
+/// Clones share the recorded accounts and the hooks.
+#[derive(Clone)]
+pub struct TrackedBanksClient {
+    banks_client: BanksClient,
+    state: TestState,
+}
+
+impl TrackedBanksClient {
+    pub(crate) fn new(banks_client: BanksClient, state: TestState) -> Self {
+        Self {
+            banks_client,
+            state,
+        }
+    }
+
+    /// Register a hook run before and after every transaction processed through the framework by the
+    /// clients of this test, including the setup transactions issued by `ClientExtensions`.
+    pub fn add_transaction_hook<H: TransactionHook + 'static>(&mut self, hook: H) {
+        self.state.hooks.add(Box::new(hook));
+    }
+
+    /// Remove all transaction hooks of this test.
+    pub fn clear_transaction_hooks(&mut self) {
+        self.state.hooks.clear();
+    }
+
+    /// Return the underlying `BanksClient`, which does not record accounts or run transaction hooks.
+    pub fn into_inner(self) -> BanksClient {
+        self.banks_client
+    }
+
+    /// Process a transaction, see `BanksClient::process_transaction`.
+    pub async fn process_transaction(
+        &mut self,
+        transaction: impl Into<VersionedTransaction>,
+    ) -> Result<(), BanksClientError> {
+        let transaction = transaction.into();
+        track_transaction_accounts(&mut self.banks_client, &self.state, &transaction).await;
+
+        self.banks_client.process_transaction(transaction).await
+    }
+
+    /// Process a transaction and return its result with metadata,
+    /// see `BanksClient::process_transaction_with_metadata`.
+    pub async fn process_transaction_with_metadata(
+        &mut self,
+        transaction: impl Into<VersionedTransaction>,
+    ) -> Result<BanksTransactionResultWithMetadata, BanksClientError> {
+        let transaction = transaction.into();
+        track_transaction_accounts(&mut self.banks_client, &self.state, &transaction).await;
+
+        self.banks_client
+            .process_transaction_with_metadata(transaction)
+            .await
+    }
+
+    /// Process a batch of transactions, see `BanksClient::process_transactions`.
+    pub async fn process_transactions(
+        &mut self,
+        transactions: Vec<impl Into<VersionedTransaction>>,
+    ) -> Result<(), BanksClientError> {
+        let transactions: Vec<VersionedTransaction> =
+            transactions.into_iter().map(Into::into).collect();
+        for transaction in &transactions {
+            track_transaction_accounts(&mut self.banks_client, &self.state, transaction).await;
+        }
+
+        self.banks_client.process_transactions(transactions).await
+    }
+}
+
+impl Deref for TrackedBanksClient {
+    type Target = BanksClient;
+
+    fn deref(&self) -> &BanksClient {
+        &self.banks_client
+    }
+}
+
+impl DerefMut for TrackedBanksClient {
+    fn deref_mut(&mut self) -> &mut BanksClient {
+        &mut self.banks_client
+    }
+}
+
+/// A `BanksClient` the `ClientExtensions` run on, with the state of its test if it is tracked.
+pub(crate) trait AsBanksClient: Send {
+    fn banks_client(&mut self) -> &mut BanksClient;
+
+    fn test_state(&self) -> Option<TestState>;
+}
+
+impl AsBanksClient for BanksClient {
+    fn banks_client(&mut self) -> &mut BanksClient {
+        self
+    }
+
+    fn test_state(&self) -> Option<TestState> {
+        None
+    }
+}
+
+impl AsBanksClient for TrackedBanksClient {
+    fn banks_client(&mut self) -> &mut BanksClient {
+        &mut self.banks_client
+    }
+
+    fn test_state(&self) -> Option<TestState> {
+        Some(self.state.clone())
+    }
+}
+
 #[async_trait]
-impl ClientExtensions for BanksClient {
+impl<C: AsBanksClient> ClientExtensions for C {
     async fn transaction_from_instructions(
         &mut self,
         ixs: &[Instruction],
         payer: &Keypair,
         signers: Vec<&Keypair>,
-    ) -> Result<Transaction, Box<dyn std::error::Error>> {
-        let latest_blockhash = self.get_latest_blockhash().await?;
+    ) -> Result<Transaction, TestFrameWorkError> {
+        let latest_blockhash = self.banks_client().get_latest_blockhash().await?;
+
+        signed_transaction(ixs, payer, &signers, latest_blockhash)
+    }
+
+    async fn send_transactions(
+        &mut self,
+        transactions: Vec<Transaction>,
+        mode: SendMode,
+    ) -> Result<BatchOutcome, TestFrameWorkError> {
+        let banks_client = self.banks_client().clone();
+        let state = self.test_state();
+
+        send_in_waves(transactions, &mode, |wave| {
+            let mut banks_client = banks_client.clone();
+            let state = state.clone();
+            async move {
+                process_transactions_with_hooks(
+                    &mut banks_client,
+                    state.as_ref(),
+                    wave.into_iter().map(Into::into).collect(),
+                )
+                .await
+                .into_iter()
+                .map(|outcome| outcome.map_err(Into::into))
+                .collect()
+            }
+        })
+        .await
+    }
+
+    async fn process_instructions(
+        &mut self,
+        ixs: &[Instruction],
+        payer: &Keypair,
+        signers: &[&Keypair],
+    ) -> Result<(), TestFrameWorkError> {
+        let latest_blockhash = self.banks_client().get_latest_blockhash().await?;
+
+        process_with_hooks(
+            self,
+            signed_transaction(ixs, payer, signers, latest_blockhash)?,
+        )
+        .await
+    }
+
+    async fn build_message(
+        &mut self,
+        builder: &TransactionBuilder,
+    ) -> Result<Message, TestFrameWorkError> {
+        let recent_blockhash =
+            resolve_recent_blockhash(self.banks_client(), builder.recent_blockhash).await?;
+
+        Ok(builder.message(recent_blockhash))
+    }
+
+    async fn build_versioned_message(
+        &mut self,
+        builder: &TransactionBuilder,
+    ) -> Result<VersionedMessage, TestFrameWorkError> {
+        let recent_blockhash =
+            resolve_recent_blockhash(self.banks_client(), builder.recent_blockhash).await?;
+
+        let mut address_lookup_tables = Vec::new();
+        for address in &builder.address_lookup_tables {
+            let data = get_account_data(self.banks_client(), *address).await?;
+            address_lookup_tables.push(lookup_table_account(*address, &data)?);
+        }
+
+        builder.versioned_message(recent_blockhash, &address_lookup_tables)
+    }
+
+    async fn process_versioned_transaction(
+        &mut self,
+        transaction: VersionedTransaction,
+    ) -> Result<(), TestFrameWorkError> {
+        process_with_hooks(self, transaction).await
+    }
+
+    async fn simulate_instructions(
+        &mut self,
+        ixs: &[Instruction],
+        payer: &Keypair,
+        signers: Vec<&Keypair>,
+        post_accounts: &[Pubkey],
+    ) -> Result<SimulationOutcome, TestFrameWorkError> {
+        if !post_accounts.is_empty() {
+            return Err(TestFrameWorkError::Error(
+                "BanksClient simulations do not report account state, request no post_accounts",
+            ));
+        }
 
-        Ok(Transaction::new_signed_with_payer(
-            ixs,
-            Some(&payer.pubkey()),
-            &signers,
-            latest_blockhash,
-        ))
+        let tx = self
+            .transaction_from_instructions(ixs, payer, signers)
+            .await?;
+        let simulation = self.banks_client().simulate_transaction(tx).await?;
+
+        let result = simulation
+            .result
+            .ok_or(TestFrameWorkError::Error("Simulation returned no result"))?;
+        let (logs, compute_units_consumed, return_data) = match simulation.simulation_details {
+            Some(details) => (details.logs, details.units_consumed, details.return_data),
+            None => (Vec::new(), 0, None),
+        };
+
+        Ok(SimulationOutcome {
+            result,
+            logs,
+            compute_units_consumed,
+            return_data,
+            post_accounts: None,
+        })
     }
 
     #[cfg(feature = "anchor")]
     async fn get_account_with_anchor<T: AccountDeserialize>(
         &mut self,
         address: Pubkey,
-    ) -> Result<T, Box<dyn std::error::Error>> {
-        self.get_account(address)
-            .map(|result| {
-                let account = result?.ok_or(BanksClientError::ClientError("Account not found"))?;
-                T::try_deserialize(&mut account.data.as_ref())
-                    .map_err(|_| BanksClientError::ClientError("Failed to deserialize account"))
-            })
-            .await
-            .map_err(Into::into)
+    ) -> Result<T, TestFrameWorkError> {
+        let account = self
+            .banks_client()
+            .get_account(address)
+            .await?
+            .ok_or(TestFrameWorkError::AccountNotFound(address))?;
+
+        T::try_deserialize(&mut account.data.as_ref())
+            .map_err(|_| TestFrameWorkError::deserialize::<T>(address))
+    }
+
+    #[cfg(feature = "idl")]
+    async fn get_account_as_json(
+        &mut self,
+        address: Pubkey,
+        idl: &AnchorIdl,
+    ) -> Result<Value, TestFrameWorkError> {
+        let data = get_account_data(self.banks_client(), address).await?;
+        Ok(idl.decode_account(&data)?.1)
     }
 
     async fn get_account_with_borsh<T: BorshDeserialize>(
         &mut self,
         address: Pubkey,
-    ) -> Result<T, Box<dyn std::error::Error>> {
-        self.get_account(address)
-            .map(|result| {
-                let account = result?.ok_or(BanksClientError::ClientError("Account not found"))?;
-                T::deserialize(&mut account.data.as_ref())
-                    .map_err(|_| BanksClientError::ClientError("Failed to deserialize account"))
-            })
-            .await
-            .map_err(Into::into)
+    ) -> Result<T, TestFrameWorkError> {
+        let account = self
+            .banks_client()
+            .get_account(address)
+            .await?
+            .ok_or(TestFrameWorkError::AccountNotFound(address))?;
+
+        T::deserialize(&mut account.data.as_ref())
+            .map_err(|_| TestFrameWorkError::deserialize::<T>(address))
+    }
+
+    async fn get_account_with_pack<P: Pack + IsInitialized>(
+        &mut self,
+        address: Pubkey,
+    ) -> Result<P, TestFrameWorkError> {
+        let account_data = get_account_data(self.banks_client(), address).await?;
+
+        P::unpack(&account_data)
+            .map_err(|_| TestFrameWorkError::deserialize::<P>(address))
+    }
+
+    async fn get_program_accounts(
+        &mut self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>, TestFrameWorkError> {
+        let state = self.test_state().ok_or(TestFrameWorkError::Error(
+            "BanksClient cannot enumerate accounts, use the TrackedBanksClient of a TrackedProgramTest",
+        ))?;
+        let mut accounts = Vec::new();
+
+        for address in state.accounts.addresses() {
+            if let Some(account) = self.banks_client().get_account(address).await? {
+                if account.owner == *program_id && matches_filters(&account, &filters) {
+                    accounts.push((address, account));
+                }
+            }
+        }
+
+        Ok(accounts)
+    }
+
+    async fn get_program_info(
+        &mut self,
+        program_id: &Pubkey,
+    ) -> Result<UpgradeableProgramInfo, TestFrameWorkError> {
+        let program = get_existing_account(self.banks_client(), *program_id).await?;
+        let programdata_address = programdata_address(*program_id, &program)?;
+        let programdata = get_existing_account(self.banks_client(), programdata_address).await?;
+
+        upgradeable_program_info(programdata_address, &programdata)
+    }
+
+    #[cfg(feature = "loader-v4")]
+    async fn get_program_v4_state(
+        &mut self,
+        program_id: &Pubkey,
+    ) -> Result<LoaderV4State, TestFrameWorkError> {
+        loader_v4_state(
+            *program_id,
+            &get_existing_account(self.banks_client(), *program_id).await?,
+        )
+    }
+
+    async fn get_mint(&mut self, address: Pubkey) -> Result<TokenState<Mint>, TestFrameWorkError> {
+        unpack_token_state(
+            address,
+            get_existing_account(self.banks_client(), address).await?,
+        )
+    }
+
+    async fn get_token_account(
+        &mut self,
+        address: Pubkey,
+    ) -> Result<TokenState<TokenAccount>, TestFrameWorkError> {
+        unpack_token_state(
+            address,
+            get_existing_account(self.banks_client(), address).await?,
+        )
     }
 
     #[cfg(feature = "pyth")]
     async fn get_pyth_price_account(
         &mut self,
         address: Pubkey,
-    ) -> Result<PriceAccount, Box<dyn std::error::Error>> {
-        let account = self.get_account(address).await?.unwrap();
+    ) -> Result<PriceAccount, TestFrameWorkError> {
+        let account = self
+            .banks_client()
+            .get_account(address)
+            .await?
+            .ok_or(TestFrameWorkError::AccountNotFound(address))?;
 
         let price_account = pyth_sdk_solana::state::load_price_account(account.data.as_ref())
-            .map_err(|_| BanksClientError::ClientError("Failed to deserialize price account"))?;
+            .map_err(|_| TestFrameWorkError::deserialize::<PriceAccount>(address))?;
         Ok(*price_account)
     }
 
@@ -69,19 +382,14 @@ impl ClientExtensions for BanksClient {
         lamports: u64,
         space: u64,
         owner: Pubkey,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let latest_blockhash = self.get_latest_blockhash().await?;
-
-        self.process_transaction(system_transaction::create_account(
-            from,
-            to,
-            latest_blockhash,
-            lamports,
-            space,
-            &owner,
-        ))
+    ) -> Result<(), TestFrameWorkError> {
+        let latest_blockhash = self.banks_client().get_latest_blockhash().await?;
+
+        process_with_hooks(
+            self,
+            system_transaction::create_account(from, to, latest_blockhash, lamports, space, &owner),
+        )
         .await
-        .map_err(Into::into)
     }
 
     async fn create_token_mint(
@@ -90,37 +398,36 @@ impl ClientExtensions for BanksClient {
         authority: &Pubkey,
         freeze_authority: Option<&Pubkey>,
         decimals: u8,
+        token_program_id: &Pubkey,
         payer: &Keypair,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let latest_blockhash = self.get_latest_blockhash().await?;
-        self.process_transaction(system_transaction::create_account(
-            payer,
-            mint,
-            latest_blockhash,
-            Rent::default().minimum_balance(spl_token::state::Mint::get_packed_len()),
-            spl_token::state::Mint::get_packed_len() as u64,
-            &spl_token::id(),
-        ))
-        .await
-        .unwrap();
+    ) -> Result<(), TestFrameWorkError> {
+        let latest_blockhash = self.banks_client().get_latest_blockhash().await?;
+        process_with_hooks(
+            self,
+            system_transaction::create_account(
+                payer,
+                mint,
+                latest_blockhash,
+                Rent::default().minimum_balance(spl_token::state::Mint::get_packed_len()),
+                spl_token::state::Mint::get_packed_len() as u64,
+                token_program_id,
+            ),
+        )
+        .await?;
 
-        let ix = spl_token::instruction::initialize_mint(
-            &spl_token::id(),
+        let ix = token_instruction::initialize_mint(
+            token_program_id,
             &mint.pubkey(),
             authority,
             freeze_authority,
             decimals,
+        )?;
+
+        process_with_hooks(
+            self,
+            signed_transaction(&[ix], payer, &[], latest_blockhash)?,
         )
-        .unwrap();
-
-        self.process_transaction(Transaction::new_signed_with_payer(
-            &[ix],
-            Some(&payer.pubkey()),
-            &[payer],
-            latest_blockhash,
-        ))
         .await
-        .map_err(Into::into)
     }
 
     async fn create_token_account(
@@ -128,36 +435,35 @@ impl ClientExtensions for BanksClient {
         account: &Keypair,
         authority: &Pubkey,
         mint: &Pubkey,
+        token_program_id: &Pubkey,
         payer: &Keypair,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let latest_blockhash = self.get_latest_blockhash().await?;
-        self.process_transaction(system_transaction::create_account(
-            payer,
-            account,
-            latest_blockhash,
-            Rent::default().minimum_balance(spl_token::state::Account::get_packed_len()),
-            spl_token::state::Account::get_packed_len() as u64,
-            &spl_token::id(),
-        ))
-        .await
-        .unwrap();
+    ) -> Result<(), TestFrameWorkError> {
+        let latest_blockhash = self.banks_client().get_latest_blockhash().await?;
+        process_with_hooks(
+            self,
+            system_transaction::create_account(
+                payer,
+                account,
+                latest_blockhash,
+                Rent::default().minimum_balance(spl_token::state::Account::get_packed_len()),
+                spl_token::state::Account::get_packed_len() as u64,
+                token_program_id,
+            ),
+        )
+        .await?;
 
-        let ix = spl_token::instruction::initialize_account(
-            &spl_token::id(),
+        let ix = token_instruction::initialize_account(
+            token_program_id,
             &account.pubkey(),
             mint,
             authority,
+        )?;
+
+        process_with_hooks(
+            self,
+            signed_transaction(&[ix], payer, &[], latest_blockhash)?,
         )
-        .unwrap();
-
-        self.process_transaction(Transaction::new_signed_with_payer(
-            &[ix],
-            Some(&payer.pubkey()),
-            &[payer],
-            latest_blockhash,
-        ))
         .await
-        .map_err(Into::into)
     }
 
     async fn create_associated_token_account(
@@ -166,172 +472,104 @@ impl ClientExtensions for BanksClient {
         mint: &Pubkey,
         payer: &Keypair,
         token_program_id: &Pubkey,
-    ) -> Result<Pubkey, Box<dyn std::error::Error>> {
-        let latest_blockhash = self.get_latest_blockhash().await?;
-        let associated_token_account = get_associated_token_address(account, mint);
+    ) -> Result<Pubkey, TestFrameWorkError> {
+        let latest_blockhash = self.banks_client().get_latest_blockhash().await?;
+        let associated_token_account =
+            get_associated_token_address_with_program_id(account, mint, token_program_id);
         let ix =
             create_associated_token_account_ix(&payer.pubkey(), account, mint, token_program_id);
 
-        self.process_transaction(Transaction::new_signed_with_payer(
-            &[ix],
-            Some(&payer.pubkey()),
-            &[payer],
-            latest_blockhash,
-        ))
+        process_with_hooks(
+            self,
+            signed_transaction(&[ix], payer, &[], latest_blockhash)?,
+        )
         .await?;
 
         return Ok(associated_token_account);
     }
 
-    async fn deploy_program(
+    async fn write_program_data(
         &mut self,
-        path_to_program: &str,
-        program_keypair: &Keypair,
+        _account: &Pubkey,
+        _data_offset: usize,
+        program_data: &[u8],
+        write_ix: &(dyn Fn(u32, Vec<u8>) -> Instruction + Sync),
         payer: &Keypair,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let (buffer, buffer_len) = util::load_file_to_bytes(path_to_program);
-
-        let program_data = buffer;
-
-        // multiply by 2 so program can be updated later on
-        let program_len = buffer_len;
-        let minimum_balance = Rent::default().minimum_balance(
-            bpf_loader_upgradeable::UpgradeableLoaderState::programdata_len(program_len)
-                .expect("Cannot get program len"),
-        );
-        let latest_blockhash = self.get_latest_blockhash().await?;
-
-        // 1 Create account
-        self.process_transaction(system_transaction::create_account(
-            payer,
-            program_keypair,
-            latest_blockhash,
-            minimum_balance,
-            program_len as u64,
-            &bpf_loader::id(),
-        ))
-        .await
-        .unwrap();
-
-        // 2. Write to buffer
-        let deploy_ix = |offset: u32, bytes: Vec<u8>| {
-            loader_instruction::write(&program_keypair.pubkey(), &bpf_loader::id(), offset, bytes)
-        };
-
-        let chunk_size = util::calculate_chunk_size(deploy_ix, &vec![payer, program_keypair]);
+        write_authority: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let chunk_size = util::calculate_chunk_size(write_ix, &vec![payer, write_authority]);
+        let latest_blockhash = self.banks_client().get_latest_blockhash().await?;
 
+        let mut transactions = Vec::new();
         for (chunk, i) in program_data.chunks(chunk_size).zip(0..) {
-            let ix = deploy_ix(i * chunk_size as u32, chunk.to_vec());
-            let tx = self
-                .transaction_from_instructions(&[ix], payer, vec![payer, program_keypair])
-                .await
-                .unwrap();
-
-            self.process_transaction(tx).await?;
-        }
-
-        // 3. Finalize
-        let finalize_tx = self
-            .transaction_from_instructions(
-                &[loader_instruction::finalize(
-                    &program_keypair.pubkey(),
-                    &bpf_loader::id(),
-                )],
+            let ix = write_ix(i * chunk_size as u32, chunk.to_vec());
+            transactions.push(signed_transaction(
+                &[ix],
                 payer,
-                vec![payer, program_keypair],
-            )
-            .await
-            .unwrap();
+                &[write_authority],
+                latest_blockhash,
+            )?);
+        }
 
-        self.process_transaction(finalize_tx).await?;
+        // the writes touch disjoint ranges of the account, so they are processed as one batch
+        let state = self.test_state();
+        let transactions = transactions.into_iter().map(Into::into).collect();
+        for outcome in
+            process_transactions_with_hooks(self.banks_client(), state.as_ref(), transactions).await
+        {
+            let outcome = outcome?;
+            outcome
+                .result
+                .map_err(|error| TestFrameWorkError::transaction(error, outcome.logs))?;
+        }
 
-        return Ok(());
+        Ok(())
     }
+}
 
-    async fn deploy_upgradable_program(
-        &mut self,
-        path_to_program: &str,
-        buffer_keypair: &Keypair,
-        buffer_authority_signer: &Keypair,
-        program_keypair: &Keypair,
-        payer: &Keypair,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let (buffer, buffer_len) = util::load_file_to_bytes(path_to_program);
-
-        let program_data = buffer;
-
-        // multiply by 2 so program can be updated later on
-        let program_len = buffer_len * 2;
-        let minimum_balance = Rent::default().minimum_balance(
-            bpf_loader_upgradeable::UpgradeableLoaderState::programdata_len(program_len)
-                .expect("Cannot get program len"),
-        );
-
-        // 1 Create buffer
-        let create_buffer_ix = bpf_loader_upgradeable::create_buffer(
-            &payer.pubkey(),
-            &buffer_keypair.pubkey(),
-            &buffer_authority_signer.pubkey(),
-            minimum_balance,
-            program_len,
-        )
-        .expect("Cannot create buffer");
-
-        let mut tx = self
-            .transaction_from_instructions(
-                create_buffer_ix.as_ref(),
-                payer,
-                vec![payer, buffer_keypair],
-            )
-            .await
-            .unwrap();
-
-        self.process_transaction(tx).await?;
-
-        // 2 Write to buffer
-        let deploy_ix = |offset: u32, bytes: Vec<u8>| {
-            bpf_loader_upgradeable::write(
-                &buffer_keypair.pubkey(),
-                &buffer_authority_signer.pubkey(),
-                offset,
-                bytes,
-            )
-        };
-
-        let chunk_size =
-            util::calculate_chunk_size(deploy_ix, &vec![payer, buffer_authority_signer]);
+/// Process a transaction, running the registered transaction hooks around it.
+async fn process_with_hooks<C: AsBanksClient + ?Sized>(
+    client: &mut C,
+    transaction: impl Into<VersionedTransaction>,
+) -> Result<(), TestFrameWorkError> {
+    let state = client.test_state();
+    let outcome =
+        process_transaction_with_hooks(client.banks_client(), state.as_ref(), transaction).await?;
+
+    outcome
+        .result
+        .map_err(|error| TestFrameWorkError::transaction(error, outcome.logs))
+}
 
-        for (chunk, i) in program_data.chunks(chunk_size).zip(0..) {
-            let ix = deploy_ix(i * chunk_size as u32, chunk.to_vec());
+/// Fetch the data of an account, failing with `AccountNotFound` if it does not exist.
+async fn get_account_data(
+    banks_client: &mut BanksClient,
+    address: Pubkey,
+) -> Result<Vec<u8>, TestFrameWorkError> {
+    Ok(get_existing_account(banks_client, address).await?.data)
+}
 
-            tx = self
-                .transaction_from_instructions(&[ix], payer, vec![payer, buffer_authority_signer])
-                .await
-                .unwrap();
+/// Fetch an account, failing if it does not exist.
+async fn get_existing_account(
+    banks_client: &mut BanksClient,
+    address: Pubkey,
+) -> Result<Account, TestFrameWorkError> {
+    banks_client
+        .get_account(address)
+        .await?
+        .ok_or(TestFrameWorkError::AccountNotFound(address))
+}
 
-            self.process_transaction(tx).await?;
+/// Resolve the recent blockhash of a transaction builder.
+async fn resolve_recent_blockhash(
+    banks_client: &mut BanksClient,
+    recent_blockhash: RecentBlockhash,
+) -> Result<Hash, TestFrameWorkError> {
+    match recent_blockhash {
+        RecentBlockhash::Latest => Ok(banks_client.get_latest_blockhash().await?),
+        RecentBlockhash::Hash(hash) => Ok(hash),
+        RecentBlockhash::Nonce { account, .. } => {
+            nonce_blockhash(account, &get_account_data(banks_client, account).await?)
         }
-
-        // 3. Finalize
-        tx = self
-            .transaction_from_instructions(
-                bpf_loader_upgradeable::deploy_with_max_program_len(
-                    &payer.pubkey(),
-                    &program_keypair.pubkey(),
-                    &buffer_keypair.pubkey(),
-                    &buffer_authority_signer.pubkey(),
-                    minimum_balance,
-                    program_len,
-                )
-                .expect("Cannot parse deploy instruction")
-                .as_ref(),
-                payer,
-                vec![payer, program_keypair, buffer_authority_signer],
-            )
-            .await
-            .unwrap();
-        self.process_transaction(tx).await?;
-
-        return Ok(());
     }
 }
@@ -0,0 +1,44 @@
+use crate::error::TestFrameWorkError;
+use solana_sdk::{account::Account, loader_v4, pubkey::Pubkey};
+
+pub use solana_sdk::loader_v4::{LoaderV4State, LoaderV4Status};
+
+// `LoaderV4State` is `repr(C)`: the slot, the authority address and the status as a `u64`
+const AUTHORITY_OFFSET: usize = 8;
+const STATUS_OFFSET: usize = AUTHORITY_OFFSET + 32;
+
+/// Read the state of a loader-v4 program account.
+pub(crate) fn loader_v4_state(
+    address: Pubkey,
+    program: &Account,
+) -> Result<LoaderV4State, TestFrameWorkError> {
+    let data = program
+        .data
+        .get(..LoaderV4State::program_data_offset())
+        .filter(|_| program.owner == loader_v4::id())
+        .ok_or_else(|| TestFrameWorkError::deserialize::<LoaderV4State>(address))?;
+
+    let read_u64 = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+    let status = match read_u64(STATUS_OFFSET) {
+        0 => LoaderV4Status::Retracted,
+        1 => LoaderV4Status::Deployed,
+        2 => LoaderV4Status::Finalized,
+        _ => return Err(TestFrameWorkError::deserialize::<LoaderV4State>(address)),
+    };
+
+    Ok(LoaderV4State {
+        slot: read_u64(0),
+        authority_address: Pubkey::try_from(&data[AUTHORITY_OFFSET..STATUS_OFFSET]).unwrap(),
+        status,
+    })
+}
+
+/// Serialize the state of a loader-v4 program account, the program data follows it in the account.
+pub(crate) fn loader_v4_state_bytes(state: &LoaderV4State) -> Vec<u8> {
+    [
+        state.slot.to_le_bytes().as_slice(),
+        state.authority_address.as_ref(),
+        (state.status as u64).to_le_bytes().as_slice(),
+    ]
+    .concat()
+}
@@ -1,40 +1,115 @@
 use async_trait::async_trait;
 use borsh::BorshDeserialize;
-use futures::FutureExt;
 use solana_program::{
-    bpf_loader_upgradeable,
-    program_pack::Pack
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    program_pack::{IsInitialized, Pack}
 };
 use solana_sdk::{
+    account::Account,
     bpf_loader,
+    hash::Hash,
     instruction::Instruction,
     loader_instruction,
+    message::{Message, VersionedMessage},
     pubkey::Pubkey,
-    signature::{Keypair, Signer},
-    system_transaction,
+    signature::{Keypair, Signature, Signer},
+    system_instruction, system_transaction,
     sysvar::rent::Rent,
-    transaction::Transaction
+    transaction::{Transaction, TransactionError, VersionedTransaction},
+    transaction_context::TransactionReturnData,
+};
+use spl_token_2022::{
+    instruction::{self as token_instruction, AuthorityType},
+    state::{Account as TokenAccount, Mint, Multisig},
 };
 use spl_associated_token_account::{
-    get_associated_token_address, instruction::create_associated_token_account as create_associated_token_account_ix,
+    get_associated_token_address_with_program_id,
+    instruction::{
+        create_associated_token_account as create_associated_token_account_ix,
+        create_associated_token_account_idempotent,
+    },
 };
 
 #[cfg(feature = "anchor")]
-use anchor_lang::AccountDeserialize;
+use anchor_lang::{AccountDeserialize, Discriminator, Event};
+
+#[cfg(feature = "idl")]
+use {crate::idl::AnchorIdl, serde_json::Value};
+
+#[cfg(feature = "loader-v4")]
+use solana_sdk::loader_v4;
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_config::{RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig},
+    rpc_response::RpcSimulateTransactionResult,
+};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_transaction_status::UiTransactionReturnData;
 
 pub use solana_banks_client::{BanksClient, BanksClientError};
 
+#[cfg(feature = "anchor")]
+mod anchor_events;
 mod banks_client;
+#[cfg(feature = "loader-v4")]
+mod loader_v4_program;
+mod program_accounts;
 mod rpc_client;
+mod rpc_send_policy;
+mod token;
+mod transaction_batch;
+mod transaction_builder;
+mod upgradeable_program;
 
+#[cfg(feature = "anchor")]
+pub use anchor_events::*;
 pub use banks_client::*;
-pub use rpc_client::*;
+#[cfg(feature = "loader-v4")]
+pub use loader_v4_program::*;
+pub use program_accounts::*;
+pub use rpc_send_policy::*;
+pub use token::*;
+pub use transaction_batch::*;
+pub use transaction_builder::*;
+pub use upgradeable_program::*;
 
-use crate::util;
+use crate::{error::TestFrameWorkError, util};
 
 #[cfg(feature = "pyth")]
 use pyth_sdk_solana::state::PriceAccount;
 
+/// Result of a processed transaction together with its execution metadata
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionOutcome {
+    pub signature: Signature,
+    pub result: Result<(), TransactionError>,
+    pub logs: Vec<String>,
+    pub compute_units_consumed: u64,
+    pub return_data: Option<TransactionReturnData>,
+}
+
+impl TransactionOutcome {
+    /// Convert the outcome into a result, keeping the logs of a failed transaction.
+    pub fn into_result(self) -> Result<(), TestFrameWorkError> {
+        self.result
+            .map_err(|error| TestFrameWorkError::transaction(error, self.logs))
+    }
+}
+
+/// Result of a simulated transaction, nothing it does is committed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulationOutcome {
+    pub result: Result<(), TransactionError>,
+    pub logs: Vec<String>,
+    pub compute_units_consumed: u64,
+    pub return_data: Option<TransactionReturnData>,
+    /// Post-simulation state of the requested accounts, in request order.
+    /// `None` on `BanksClient`, whose simulation does not report account state.
+    pub post_accounts: Option<Vec<Option<Account>>>,
+}
+
 /// Convenience functions for clients
 #[async_trait]
 pub trait ClientExtensions {
@@ -45,35 +120,196 @@ pub trait ClientExtensions {
         _ixs: &[Instruction],
         _payer: &Keypair,
         _signers: Vec<&Keypair>,
-    ) -> Result<Transaction, Box<dyn std::error::Error>> {
+    ) -> Result<Transaction, TestFrameWorkError> {
+        unimplemented!();
+    }
+
+    /// Simulate a transaction built from the given instructions without committing its state changes,
+    /// returning the post-simulation state of the accounts at `post_accounts`.
+    /// `BanksClient` simulations do not report account state and fail if any account is requested.
+    async fn simulate_instructions(
+        &mut self,
+        _ixs: &[Instruction],
+        _payer: &Keypair,
+        _signers: Vec<&Keypair>,
+        _post_accounts: &[Pubkey],
+    ) -> Result<SimulationOutcome, TestFrameWorkError> {
+        unimplemented!();
+    }
+
+    /// Compile the unsigned message described by the builder, resolving its recent blockhash or durable nonce.
+    async fn build_message(
+        &mut self,
+        _builder: &TransactionBuilder,
+    ) -> Result<Message, TestFrameWorkError> {
+        unimplemented!();
+    }
+
+    /// Build the transaction described by the builder and sign it with all required signers.
+    async fn build_transaction(
+        &mut self,
+        builder: &TransactionBuilder,
+        signers: &[&Keypair],
+    ) -> Result<Transaction, TestFrameWorkError> {
+        let mut transaction = Transaction::new_unsigned(self.build_message(builder).await?);
+        let recent_blockhash = transaction.message.recent_blockhash;
+        transaction.try_sign(signers, recent_blockhash)?;
+
+        Ok(transaction)
+    }
+
+    /// Build the transaction described by the builder and sign it with the given subset of the signers.
+    async fn build_partially_signed_transaction(
+        &mut self,
+        builder: &TransactionBuilder,
+        signers: &[&Keypair],
+    ) -> Result<Transaction, TestFrameWorkError> {
+        let mut transaction = Transaction::new_unsigned(self.build_message(builder).await?);
+        let recent_blockhash = transaction.message.recent_blockhash;
+        transaction.try_partial_sign(signers, recent_blockhash)?;
+
+        Ok(transaction)
+    }
+
+    /// Compile the unsigned v0 message described by the builder, resolving its address lookup tables from the chain.
+    async fn build_versioned_message(
+        &mut self,
+        _builder: &TransactionBuilder,
+    ) -> Result<VersionedMessage, TestFrameWorkError> {
+        unimplemented!();
+    }
+
+    /// Build the v0 transaction described by the builder and sign it with all required signers.
+    async fn build_versioned_transaction(
+        &mut self,
+        builder: &TransactionBuilder,
+        signers: &[&Keypair],
+    ) -> Result<VersionedTransaction, TestFrameWorkError> {
+        let message = self.build_versioned_message(builder).await?;
+
+        Ok(VersionedTransaction::try_new(message, signers)?)
+    }
+
+    /// Send a versioned transaction and wait until it is executed.
+    async fn process_versioned_transaction(
+        &mut self,
+        _transaction: VersionedTransaction,
+    ) -> Result<(), TestFrameWorkError> {
         unimplemented!();
     }
 
     /// Return and deserialize an Anchor account at the given address at the time of the most recent root slot.
-    /// If the account is not found, `None` is returned.
+    /// Returns `TestFrameWorkError::AccountNotFound` if the account does not exist
+    /// and `TestFrameWorkError::Deserialize` if its data is not a `T`.
     #[cfg(feature = "anchor")]
     async fn get_account_with_anchor<T: AccountDeserialize>(
         &mut self,
         _address: Pubkey,
-    ) -> Result<T, Box<dyn std::error::Error>> {
+    ) -> Result<T, TestFrameWorkError> {
+        unimplemented!();
+    }
+
+    /// Decode the Anchor events of type `T` emitted by a transaction, from its `Program data:` logs and,
+    /// when fetched by signature, from `emit_cpi!` self-CPI inner instructions.
+    #[cfg(feature = "anchor")]
+    async fn get_anchor_events<T: Event + Send, S: Into<EventSource> + Send>(
+        &mut self,
+        source: S,
+    ) -> Result<Vec<T>, TestFrameWorkError> {
+        match source.into() {
+            EventSource::Logs(logs) => parse_anchor_events(&logs),
+            EventSource::Signature(_) => Err(TestFrameWorkError::Error(
+                "This client cannot fetch transaction logs by signature",
+            )),
+        }
+    }
+
+    /// Return the account at the given address decoded into JSON with an Anchor IDL,
+    /// identifying its type by the account discriminator.
+    #[cfg(feature = "idl")]
+    async fn get_account_as_json(
+        &mut self,
+        _address: Pubkey,
+        _idl: &AnchorIdl,
+    ) -> Result<Value, TestFrameWorkError> {
         unimplemented!();
     }
 
     /// Return and deserialize a Borsh account at the given address at the time of the most recent root slot.
-    /// If the account is not `found`, None is returned.
+    /// Returns `TestFrameWorkError::AccountNotFound` if the account does not exist
+    /// and `TestFrameWorkError::Deserialize` if its data is not a `T`.
     async fn get_account_with_borsh<T: BorshDeserialize>(
         &mut self,
         _address: Pubkey,
-    ) -> Result<T, Box<dyn std::error::Error>> {
+    ) -> Result<T, TestFrameWorkError> {
         unimplemented!();
     }
 
+    /// Return and deserialize a `Pack` account at the given address, the data length must be exactly `P::LEN`.
+    /// Returns `TestFrameWorkError::Deserialize` if the account is not initialized.
+    async fn get_account_with_pack<P: Pack + IsInitialized>(
+        &mut self,
+        _address: Pubkey,
+    ) -> Result<P, TestFrameWorkError> {
+        unimplemented!();
+    }
+
+    /// Return all accounts owned by `program_id` matching all the filters.
+    /// `BanksClient` cannot enumerate accounts, only a `TrackedBanksClient` finds accounts by scanning the
+    /// addresses its test recorded, a plain `BanksClient` returns an error.
+    async fn get_program_accounts(
+        &mut self,
+        _program_id: &Pubkey,
+        _filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>, TestFrameWorkError> {
+        unimplemented!();
+    }
+
+    /// Return and deserialize all Anchor accounts of type `T` owned by `program_id` matching all the filters.
+    #[cfg(feature = "anchor")]
+    async fn get_program_accounts_with_anchor<T: AccountDeserialize + Discriminator + Send>(
+        &mut self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, T)>, TestFrameWorkError> {
+        let filters = std::iter::once(anchor_discriminator_filter::<T>())
+            .chain(filters)
+            .collect();
+
+        self.get_program_accounts(program_id, filters)
+            .await?
+            .into_iter()
+            .map(|(address, account)| {
+                T::try_deserialize(&mut account.data.as_ref())
+                    .map(|state| (address, state))
+                    .map_err(|_| TestFrameWorkError::deserialize::<T>(address))
+            })
+            .collect()
+    }
+
+    /// Return an SPL Token or Token-2022 mint at the given address, with its extensions
+    async fn get_mint(&mut self, _address: Pubkey) -> Result<TokenState<Mint>, TestFrameWorkError> {
+        unimplemented!();
+    }
+
+    /// Return an SPL Token or Token-2022 token account at the given address, with its extensions
+    async fn get_token_account(
+        &mut self,
+        _address: Pubkey,
+    ) -> Result<TokenState<TokenAccount>, TestFrameWorkError> {
+        unimplemented!();
+    }
+
+    /// Return the token amount held by an SPL Token or Token-2022 token account
+    async fn get_token_balance(&mut self, address: Pubkey) -> Result<u64, TestFrameWorkError> {
+        Ok(self.get_token_account(address).await?.base().amount)
+    }
 
     #[cfg(feature = "pyth")]
     async fn get_pyth_price_account(
         &mut self,
         _address: Pubkey,
-    ) -> Result<PriceAccount, Box<dyn std::error::Error>> {
+    ) -> Result<PriceAccount, TestFrameWorkError> {
         unimplemented!();
     }
 
@@ -85,30 +321,32 @@ pub trait ClientExtensions {
         _lamports: u64,
         _space: u64,
         _owner: Pubkey,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    ) -> Result<(), TestFrameWorkError> {
         unimplemented!();
     }
 
-    /// Create a new SPL Token Mint account
+    /// Create a new SPL Token or Token-2022 Mint account, owned by `token_program_id`
     async fn create_token_mint(
         &mut self,
         _mint: &Keypair,
         _authority: &Pubkey,
         _freeze_authority: Option<&Pubkey>,
         _decimals: u8,
+        _token_program_id: &Pubkey,
         _payer: &Keypair,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    ) -> Result<(), TestFrameWorkError> {
         unimplemented!();
     }
 
-    /// Create a new SPL Token Account
+    /// Create a new SPL Token or Token-2022 Account, owned by `token_program_id`
     async fn create_token_account(
         &mut self,
         _account: &Keypair,
         _authority: &Pubkey,
         _mint: &Pubkey,
+        _token_program_id: &Pubkey,
         _payer: &Keypair,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    ) -> Result<(), TestFrameWorkError> {
         unimplemented!();
     }
 
@@ -119,29 +357,817 @@ pub trait ClientExtensions {
         _mint: &Pubkey,
         _payer: &Keypair,
         _token_program_id: &Pubkey,
-    ) -> Result<Pubkey, Box<dyn std::error::Error>> {
+    ) -> Result<Pubkey, TestFrameWorkError> {
         unimplemented!();
     }
 
-    /// Deploy a program
+    /// Deploy a program with the non-upgradeable BPF loader
     async fn deploy_program(
         &mut self,
-        _path_to_program: &str,
-        _program_keypair: &Keypair,
-        _payer: &Keypair,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        unimplemented!();
+        path_to_program: &str,
+        program_keypair: &Keypair,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let program_data = util::read_program_file(path_to_program)?;
+
+        self.deploy_program_from_bytes(&program_data, program_keypair, payer)
+            .await
     }
 
-    /// Deploy an upgradable program
+    /// Deploy a program held in memory with the non-upgradeable BPF loader, the ELF is verified before anything is sent
+    async fn deploy_program_from_bytes(
+        &mut self,
+        program_data: &[u8],
+        program_keypair: &Keypair,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        util::verify_elf(program_data)?;
+
+        // 1. Create the program account
+        let create_account_ix = system_instruction::create_account(
+            &payer.pubkey(),
+            &program_keypair.pubkey(),
+            Rent::default().minimum_balance(program_data.len()),
+            program_data.len() as u64,
+            &bpf_loader::id(),
+        );
+        self.process_instructions(&[create_account_ix], payer, &[program_keypair])
+            .await?;
+
+        // 2. Write the program data
+        let program_id = program_keypair.pubkey();
+        let write_ix = |offset: u32, bytes: Vec<u8>| {
+            loader_instruction::write(&program_id, &bpf_loader::id(), offset, bytes)
+        };
+        self.write_program_data(
+            &program_id,
+            0,
+            program_data,
+            &write_ix,
+            payer,
+            program_keypair,
+        )
+        .await?;
+
+        // 3. Finalize
+        let finalize_ix =
+            loader_instruction::finalize(&program_keypair.pubkey(), &bpf_loader::id());
+        self.process_instructions(&[finalize_ix], payer, &[program_keypair])
+            .await
+    }
+
+    /// Deploy an upgradable program, with room to grow to twice its size on upgrades
     async fn deploy_upgradable_program(
         &mut self,
-        _path_to_program: &str,
-        _buffer_keypair: &Keypair,
-        _buffer_authority_signer: &Keypair,
-        _program_keypair: &Keypair,
+        path_to_program: &str,
+        buffer_keypair: &Keypair,
+        buffer_authority_signer: &Keypair,
+        program_keypair: &Keypair,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let program_data = util::read_program_file(path_to_program)?;
+
+        self.deploy_upgradable_program_from_bytes(
+            &program_data,
+            buffer_keypair,
+            buffer_authority_signer,
+            program_keypair,
+            None,
+            payer,
+        )
+        .await
+    }
+
+    /// Deploy an upgradable program held in memory, the ELF is verified before anything is sent.
+    /// `max_program_len` sizes the program data account for later upgrades, defaulting to twice the program length.
+    async fn deploy_upgradable_program_from_bytes(
+        &mut self,
+        program_data: &[u8],
+        buffer_keypair: &Keypair,
+        buffer_authority_signer: &Keypair,
+        program_keypair: &Keypair,
+        max_program_len: Option<usize>,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        util::verify_elf(program_data)?;
+
+        let max_program_len = max_program_len.unwrap_or(program_data.len() * 2);
+        if max_program_len < program_data.len() {
+            return Err(TestFrameWorkError::InvalidProgram(format!(
+                "max program length {} is smaller than the program length {}",
+                max_program_len,
+                program_data.len()
+            )));
+        }
+
+        // 1. Create and fill the buffer
+        self.write_program_buffer(buffer_keypair, buffer_authority_signer, program_data, payer)
+            .await?;
+
+        // 2. Deploy from the buffer
+        let deploy_ixs = bpf_loader_upgradeable::deploy_with_max_program_len(
+            &payer.pubkey(),
+            &program_keypair.pubkey(),
+            &buffer_keypair.pubkey(),
+            &buffer_authority_signer.pubkey(),
+            Rent::default().minimum_balance(UpgradeableLoaderState::size_of_program()),
+            max_program_len,
+        )?;
+        self.process_instructions(
+            &deploy_ixs,
+            payer,
+            &[program_keypair, buffer_authority_signer],
+        )
+        .await
+    }
+
+    /// Create a buffer account of the upgradeable BPF loader and write the program data into it
+    async fn write_program_buffer(
+        &mut self,
+        buffer_keypair: &Keypair,
+        buffer_authority: &Keypair,
+        program_data: &[u8],
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let buffer_len = UpgradeableLoaderState::size_of_buffer(program_data.len());
+        let create_buffer_ixs = bpf_loader_upgradeable::create_buffer(
+            &payer.pubkey(),
+            &buffer_keypair.pubkey(),
+            &buffer_authority.pubkey(),
+            Rent::default().minimum_balance(buffer_len),
+            program_data.len(),
+        )?;
+        self.process_instructions(&create_buffer_ixs, payer, &[buffer_keypair])
+            .await?;
+
+        let buffer = buffer_keypair.pubkey();
+        let authority = buffer_authority.pubkey();
+        let write_ix = |offset: u32, bytes: Vec<u8>| {
+            bpf_loader_upgradeable::write(&buffer, &authority, offset, bytes)
+        };
+
+        self.write_program_data(
+            &buffer,
+            UpgradeableLoaderState::size_of_buffer_metadata(),
+            program_data,
+            &write_ix,
+            payer,
+            buffer_authority,
+        )
+        .await
+    }
+
+    /// Write `program_data` into a program or buffer account in chunks of one transaction each, with the
+    /// loader `write_ix` for a chunk at an offset. The data starts at `data_offset` in the account.
+    /// Chunks are sent one after the other by default, `BanksClient` processes them as one batch and the
+    /// `RpcClient` backends retry the chunks missing from the account, the nonblocking one sends them concurrently.
+    async fn write_program_data(
+        &mut self,
+        _account: &Pubkey,
+        _data_offset: usize,
+        program_data: &[u8],
+        write_ix: &(dyn Fn(u32, Vec<u8>) -> Instruction + Sync),
+        payer: &Keypair,
+        write_authority: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let chunk_size = util::calculate_chunk_size(write_ix, &vec![payer, write_authority]);
+
+        for (chunk, i) in program_data.chunks(chunk_size).zip(0..) {
+            let ix = write_ix(i * chunk_size as u32, chunk.to_vec());
+            self.process_instructions(&[ix], payer, &[write_authority])
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Upgrade an upgradeable program to the program at `path_to_program`, written to a new buffer first.
+    /// A larger program must be made to fit with `extend_program` first, in an earlier slot.
+    async fn upgrade_program(
+        &mut self,
+        program_id: &Pubkey,
+        path_to_program: &str,
+        buffer_keypair: &Keypair,
+        upgrade_authority: &Keypair,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let program_data = util::read_program_file(path_to_program)?;
+        util::verify_elf(&program_data)?;
+        if program_data.len() > self.get_program_info(program_id).await?.data_len {
+            return Err(TestFrameWorkError::Error(
+                "Program does not fit into the program data account, extend it first",
+            ));
+        }
+
+        self.write_program_buffer(buffer_keypair, upgrade_authority, &program_data, payer)
+            .await?;
+
+        let ix = bpf_loader_upgradeable::upgrade(
+            program_id,
+            &buffer_keypair.pubkey(),
+            &upgrade_authority.pubkey(),
+            &payer.pubkey(),
+        );
+        self.process_instructions(&[ix], payer, &[upgrade_authority])
+            .await
+    }
+
+    /// Set the upgrade authority of an upgradeable program, `None` makes the program immutable
+    async fn set_upgrade_authority(
+        &mut self,
+        program_id: &Pubkey,
+        upgrade_authority: &Keypair,
+        new_authority: Option<&Pubkey>,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let ix = bpf_loader_upgradeable::set_upgrade_authority(
+            program_id,
+            &upgrade_authority.pubkey(),
+            new_authority,
+        );
+
+        self.process_instructions(&[ix], payer, &[upgrade_authority])
+            .await
+    }
+
+    /// Extend the program data account of an upgradeable program by `additional_bytes`, paid for by the payer
+    async fn extend_program(
+        &mut self,
+        program_id: &Pubkey,
+        additional_bytes: u32,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let ix = bpf_loader_upgradeable::extend_program(
+            program_id,
+            Some(&payer.pubkey()),
+            additional_bytes,
+        );
+
+        self.process_instructions(&[ix], payer, &[]).await
+    }
+
+    /// Close an upgradeable program, sending the lamports of its program data account to `recipient`.
+    /// A closed program cannot be invoked or redeployed at the same address.
+    async fn close_program(
+        &mut self,
+        program_id: &Pubkey,
+        upgrade_authority: &Keypair,
+        recipient: &Pubkey,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let programdata_address = self.get_program_info(program_id).await?.programdata_address;
+        let ix = bpf_loader_upgradeable::close_any(
+            &programdata_address,
+            recipient,
+            Some(&upgrade_authority.pubkey()),
+            Some(program_id),
+        );
+
+        self.process_instructions(&[ix], payer, &[upgrade_authority])
+            .await
+    }
+
+    /// Close a buffer account of the upgradeable BPF loader, sending its lamports to `recipient`
+    async fn close_buffer(
+        &mut self,
+        buffer: &Pubkey,
+        buffer_authority: &Keypair,
+        recipient: &Pubkey,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let ix = bpf_loader_upgradeable::close_any(
+            buffer,
+            recipient,
+            Some(&buffer_authority.pubkey()),
+            None,
+        );
+
+        self.process_instructions(&[ix], payer, &[buffer_authority])
+            .await
+    }
+
+    /// Return the upgrade authority, last deploy slot and data length of an upgradeable program
+    async fn get_program_info(
+        &mut self,
+        _program_id: &Pubkey,
+    ) -> Result<UpgradeableProgramInfo, TestFrameWorkError> {
+        unimplemented!();
+    }
+
+    /// Create a loader-v4 program account and write the program data into it, the account stays retracted.
+    /// Used as the program account by `deploy_program_v4` and as the source of `upgrade_program_v4`.
+    #[cfg(feature = "loader-v4")]
+    async fn write_program_v4_buffer(
+        &mut self,
+        buffer_keypair: &Keypair,
+        authority: &Keypair,
+        program_data: &[u8],
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let buffer = buffer_keypair.pubkey();
+        let create_buffer_ixs = loader_v4::create_buffer(
+            &payer.pubkey(),
+            &buffer,
+            Rent::default()
+                .minimum_balance(LoaderV4State::program_data_offset() + program_data.len()),
+            &authority.pubkey(),
+            program_data.len() as u32,
+            &payer.pubkey(),
+        );
+        self.process_instructions(&create_buffer_ixs, payer, &[buffer_keypair, authority])
+            .await?;
+
+        let authority_address = authority.pubkey();
+        let write_ix = |offset: u32, bytes: Vec<u8>| {
+            loader_v4::write(&buffer, &authority_address, offset, bytes)
+        };
+
+        self.write_program_data(
+            &buffer,
+            LoaderV4State::program_data_offset(),
+            program_data,
+            &write_ix,
+            payer,
+            authority,
+        )
+        .await
+    }
+
+    /// Deploy a program with the loader-v4 program, `authority` can upgrade and retract it.
+    /// The debug assertions of solana-runtime must be turned off in the workspace profile,
+    /// otherwise the runtime panics when it loads the program.
+    #[cfg(feature = "loader-v4")]
+    async fn deploy_program_v4(
+        &mut self,
+        program_keypair: &Keypair,
+        authority: &Keypair,
+        program_data: &[u8],
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        self.write_program_v4_buffer(program_keypair, authority, program_data, payer)
+            .await?;
+
+        let ix = loader_v4::deploy(&program_keypair.pubkey(), &authority.pubkey());
+        self.process_instructions(&[ix], payer, &[authority]).await
+    }
+
+    /// Replace a loader-v4 program with new program data, written to a buffer first.
+    /// A deployed program is retracted and redeployed in one transaction, which the loader only allows
+    /// `loader_v4::DEPLOYMENT_COOLDOWN_IN_SLOTS` after its last deployment.
+    #[cfg(feature = "loader-v4")]
+    async fn upgrade_program_v4(
+        &mut self,
+        program_id: &Pubkey,
+        buffer_keypair: &Keypair,
+        authority: &Keypair,
+        program_data: &[u8],
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let state = self.get_program_v4_state(program_id).await?;
+        self.write_program_v4_buffer(buffer_keypair, authority, program_data, payer)
+            .await?;
+
+        let mut ixs = Vec::new();
+        if state.status == LoaderV4Status::Deployed {
+            ixs.push(loader_v4::retract(program_id, &authority.pubkey()));
+        }
+        ixs.push(loader_v4::deploy_from_source(
+            program_id,
+            &authority.pubkey(),
+            &buffer_keypair.pubkey(),
+        ));
+
+        self.process_instructions(&ixs, payer, &[authority]).await
+    }
+
+    /// Retract a deployed loader-v4 program, it can no longer be invoked until it is deployed again
+    #[cfg(feature = "loader-v4")]
+    async fn retract_program_v4(
+        &mut self,
+        program_id: &Pubkey,
+        authority: &Keypair,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let ix = loader_v4::retract(program_id, &authority.pubkey());
+
+        self.process_instructions(&[ix], payer, &[authority]).await
+    }
+
+    /// Return the authority, status and last deployment slot of a loader-v4 program
+    #[cfg(feature = "loader-v4")]
+    async fn get_program_v4_state(
+        &mut self,
+        _program_id: &Pubkey,
+    ) -> Result<LoaderV4State, TestFrameWorkError> {
+        unimplemented!();
+    }
+
+    /// Send a batch of signed transactions in the given mode and wait until they are executed.
+    /// Returns the outcome of every transaction in batch order, or an error for a transaction that
+    /// could not be sent, or was skipped because a transaction it depends on failed.
+    async fn send_transactions(
+        &mut self,
+        _transactions: Vec<Transaction>,
+        _mode: SendMode,
+    ) -> Result<BatchOutcome, TestFrameWorkError> {
+        unimplemented!();
+    }
+
+    /// Send the given instructions in one transaction, paid for by the payer and signed by the payer and the signers.
+    async fn process_instructions(
+        &mut self,
+        _ixs: &[Instruction],
         _payer: &Keypair,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+        _signers: &[&Keypair],
+    ) -> Result<(), TestFrameWorkError> {
         unimplemented!();
     }
-}
\ No newline at end of file
+
+    /// Create a new SPL Token multisig account requiring `m` of the given signers
+    async fn create_token_multisig(
+        &mut self,
+        multisig: &Keypair,
+        signers: &[&Pubkey],
+        m: u8,
+        token_program_id: &Pubkey,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let ixs = [
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &multisig.pubkey(),
+                Rent::default().minimum_balance(Multisig::LEN),
+                Multisig::LEN as u64,
+                token_program_id,
+            ),
+            token_instruction::initialize_multisig2(
+                token_program_id,
+                &multisig.pubkey(),
+                signers,
+                m,
+            )?,
+        ];
+
+        self.process_instructions(&ixs, payer, &[multisig]).await
+    }
+
+    /// Mint SPL tokens to a token account
+    async fn mint_to(
+        &mut self,
+        token_program_id: &Pubkey,
+        mint: &Pubkey,
+        account: &Pubkey,
+        authority: &TokenAuthority<'_>,
+        amount: u64,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let ix = token_instruction::mint_to(
+            token_program_id,
+            mint,
+            account,
+            &authority.address,
+            &authority.multisig_signers(),
+            amount,
+        )?;
+
+        self.process_instructions(&[ix], payer, &authority.signers)
+            .await
+    }
+
+    /// Transfer SPL tokens between token accounts
+    async fn transfer(
+        &mut self,
+        token_program_id: &Pubkey,
+        source: &Pubkey,
+        destination: &Pubkey,
+        authority: &TokenAuthority<'_>,
+        amount: u64,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        #[allow(deprecated)]
+        let ix = token_instruction::transfer(
+            token_program_id,
+            source,
+            destination,
+            &authority.address,
+            &authority.multisig_signers(),
+            amount,
+        )?;
+
+        self.process_instructions(&[ix], payer, &authority.signers)
+            .await
+    }
+
+    /// Transfer SPL tokens between token accounts, checking the mint and its decimals
+    #[allow(clippy::too_many_arguments)]
+    async fn transfer_checked(
+        &mut self,
+        token_program_id: &Pubkey,
+        source: &Pubkey,
+        mint: &Pubkey,
+        destination: &Pubkey,
+        authority: &TokenAuthority<'_>,
+        amount: u64,
+        decimals: u8,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let ix = token_instruction::transfer_checked(
+            token_program_id,
+            source,
+            mint,
+            destination,
+            &authority.address,
+            &authority.multisig_signers(),
+            amount,
+            decimals,
+        )?;
+
+        self.process_instructions(&[ix], payer, &authority.signers)
+            .await
+    }
+
+    /// Approve a delegate to transfer or burn up to `amount` tokens of a token account
+    async fn approve(
+        &mut self,
+        token_program_id: &Pubkey,
+        source: &Pubkey,
+        delegate: &Pubkey,
+        authority: &TokenAuthority<'_>,
+        amount: u64,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let ix = token_instruction::approve(
+            token_program_id,
+            source,
+            delegate,
+            &authority.address,
+            &authority.multisig_signers(),
+            amount,
+        )?;
+
+        self.process_instructions(&[ix], payer, &authority.signers)
+            .await
+    }
+
+    /// Revoke the delegate of a token account
+    async fn revoke(
+        &mut self,
+        token_program_id: &Pubkey,
+        source: &Pubkey,
+        authority: &TokenAuthority<'_>,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let ix = token_instruction::revoke(
+            token_program_id,
+            source,
+            &authority.address,
+            &authority.multisig_signers(),
+        )?;
+
+        self.process_instructions(&[ix], payer, &authority.signers)
+            .await
+    }
+
+    /// Burn SPL tokens from a token account
+    async fn burn(
+        &mut self,
+        token_program_id: &Pubkey,
+        account: &Pubkey,
+        mint: &Pubkey,
+        authority: &TokenAuthority<'_>,
+        amount: u64,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let ix = token_instruction::burn(
+            token_program_id,
+            account,
+            mint,
+            &authority.address,
+            &authority.multisig_signers(),
+            amount,
+        )?;
+
+        self.process_instructions(&[ix], payer, &authority.signers)
+            .await
+    }
+
+    /// Freeze a token account with the freeze authority of its mint
+    async fn freeze_account(
+        &mut self,
+        token_program_id: &Pubkey,
+        account: &Pubkey,
+        mint: &Pubkey,
+        authority: &TokenAuthority<'_>,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let ix = token_instruction::freeze_account(
+            token_program_id,
+            account,
+            mint,
+            &authority.address,
+            &authority.multisig_signers(),
+        )?;
+
+        self.process_instructions(&[ix], payer, &authority.signers)
+            .await
+    }
+
+    /// Thaw a frozen token account with the freeze authority of its mint
+    async fn thaw_account(
+        &mut self,
+        token_program_id: &Pubkey,
+        account: &Pubkey,
+        mint: &Pubkey,
+        authority: &TokenAuthority<'_>,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let ix = token_instruction::thaw_account(
+            token_program_id,
+            account,
+            mint,
+            &authority.address,
+            &authority.multisig_signers(),
+        )?;
+
+        self.process_instructions(&[ix], payer, &authority.signers)
+            .await
+    }
+
+    /// Close a token account, sending its lamports to `destination`
+    async fn close_account(
+        &mut self,
+        token_program_id: &Pubkey,
+        account: &Pubkey,
+        destination: &Pubkey,
+        authority: &TokenAuthority<'_>,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let ix = token_instruction::close_account(
+            token_program_id,
+            account,
+            destination,
+            &authority.address,
+            &authority.multisig_signers(),
+        )?;
+
+        self.process_instructions(&[ix], payer, &authority.signers)
+            .await
+    }
+
+    /// Set or remove an authority of a mint or token account
+    #[allow(clippy::too_many_arguments)]
+    async fn set_authority(
+        &mut self,
+        token_program_id: &Pubkey,
+        owned: &Pubkey,
+        new_authority: Option<&Pubkey>,
+        authority_type: AuthorityType,
+        authority: &TokenAuthority<'_>,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let ix = token_instruction::set_authority(
+            token_program_id,
+            owned,
+            new_authority,
+            authority_type,
+            &authority.address,
+            &authority.multisig_signers(),
+        )?;
+
+        self.process_instructions(&[ix], payer, &authority.signers)
+            .await
+    }
+
+    /// Wrap `lamports` of the owner into its associated token account of the native mint of the token program,
+    /// creating it if needed. Returns the address of the associated token account.
+    async fn wrap_sol(
+        &mut self,
+        token_program_id: &Pubkey,
+        owner: &Keypair,
+        lamports: u64,
+    ) -> Result<Pubkey, TestFrameWorkError> {
+        let native_mint = native_mint(token_program_id);
+        let account = get_associated_token_address_with_program_id(
+            &owner.pubkey(),
+            &native_mint,
+            token_program_id,
+        );
+        let ixs = [
+            create_associated_token_account_idempotent(
+                &owner.pubkey(),
+                &owner.pubkey(),
+                &native_mint,
+                token_program_id,
+            ),
+            system_instruction::transfer(&owner.pubkey(), &account, lamports),
+            token_instruction::sync_native(token_program_id, &account)?,
+        ];
+
+        self.process_instructions(&ixs, owner, &[]).await?;
+        Ok(account)
+    }
+
+    /// Close the native mint associated token account of the owner, returning all its lamports to the owner
+    async fn unwrap_sol(
+        &mut self,
+        token_program_id: &Pubkey,
+        owner: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let account = get_associated_token_address_with_program_id(
+            &owner.pubkey(),
+            &native_mint(token_program_id),
+            token_program_id,
+        );
+        let ix = token_instruction::close_account(
+            token_program_id,
+            &account,
+            &owner.pubkey(),
+            &owner.pubkey(),
+            &[],
+        )?;
+
+        self.process_instructions(&[ix], owner, &[]).await
+    }
+
+    /// Sync the token amount of a native token account with its lamports
+    async fn sync_native(
+        &mut self,
+        token_program_id: &Pubkey,
+        account: &Pubkey,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        let ix = token_instruction::sync_native(token_program_id, account)?;
+
+        self.process_instructions(&[ix], payer, &[]).await
+    }
+}
+
+/// Sign a transaction with the payer and the signers.
+fn signed_transaction(
+    ixs: &[Instruction],
+    payer: &Keypair,
+    signers: &[&Keypair],
+    recent_blockhash: Hash,
+) -> Result<Transaction, TestFrameWorkError> {
+    let mut all_signers = vec![payer];
+    all_signers.extend_from_slice(signers);
+
+    let mut transaction = Transaction::new_with_payer(ixs, Some(&payer.pubkey()));
+    transaction.try_sign(&all_signers, recent_blockhash)?;
+
+    Ok(transaction)
+}
+
+/// Map an RPC simulation result, the requested accounts are returned base64 encoded.
+fn simulation_outcome_from_rpc(
+    simulation: RpcSimulateTransactionResult,
+) -> Result<SimulationOutcome, TestFrameWorkError> {
+    let post_accounts = simulation.accounts.map(|accounts| {
+        accounts
+            .into_iter()
+            .map(|account| account.and_then(|account| account.decode::<Account>()))
+            .collect()
+    });
+
+    Ok(SimulationOutcome {
+        result: simulation.err.map_or(Ok(()), Err),
+        logs: simulation.logs.unwrap_or_default(),
+        compute_units_consumed: simulation.units_consumed.unwrap_or_default(),
+        return_data: return_data_from_rpc(simulation.return_data)?,
+        post_accounts,
+    })
+}
+
+/// Map return data reported by an RPC node, its data is base64 encoded.
+fn return_data_from_rpc(
+    return_data: Option<UiTransactionReturnData>,
+) -> Result<Option<TransactionReturnData>, TestFrameWorkError> {
+    return_data
+        .map(|return_data| {
+            Ok(TransactionReturnData {
+                program_id: return_data
+                    .program_id
+                    .parse()
+                    .map_err(|_| TestFrameWorkError::Error("Invalid return data program id"))?,
+                data: BASE64_STANDARD
+                    .decode(return_data.data.0)
+                    .map_err(|_| TestFrameWorkError::Error("Invalid return data encoding"))?,
+            })
+        })
+        .transpose()
+}
+
+/// RPC simulation config returning the state of `post_accounts`.
+fn rpc_simulate_config(
+    post_accounts: &[Pubkey],
+    commitment: CommitmentConfig,
+) -> RpcSimulateTransactionConfig {
+    RpcSimulateTransactionConfig {
+        commitment: Some(commitment),
+        accounts: Some(RpcSimulateTransactionAccountsConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            addresses: post_accounts.iter().map(Pubkey::to_string).collect(),
+        }),
+        ..RpcSimulateTransactionConfig::default()
+    }
+}
@@ -0,0 +1,38 @@
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    commitment_config::CommitmentConfig,
+};
+
+pub use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+
+#[cfg(feature = "anchor")]
+use anchor_lang::Discriminator;
+
+/// Filter selecting the Anchor accounts of type `T` by their 8 byte discriminator.
+#[cfg(feature = "anchor")]
+pub fn anchor_discriminator_filter<T: Discriminator>() -> RpcFilterType {
+    RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, T::DISCRIMINATOR.to_vec()))
+}
+
+/// Whether the account matches all the filters, the same way the RPC server applies them.
+pub(crate) fn matches_filters(account: &Account, filters: &[RpcFilterType]) -> bool {
+    let account = AccountSharedData::from(account.clone());
+    filters.iter().all(|filter| filter.allows(&account))
+}
+
+pub(crate) fn rpc_program_accounts_config(
+    filters: Vec<RpcFilterType>,
+    commitment: CommitmentConfig,
+) -> RpcProgramAccountsConfig {
+    RpcProgramAccountsConfig {
+        filters: (!filters.is_empty()).then_some(filters),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            commitment: Some(commitment),
+            ..RpcAccountInfoConfig::default()
+        },
+        with_context: None,
+    }
+}
@@ -1,339 +1,559 @@
 use super::*;
-use solana_client::rpc_client::RpcClient;
+use futures::stream::{self, StreamExt};
 
 #[cfg(feature = "pyth")]
 use pyth_sdk_solana::state::PriceAccount;
 
-#[async_trait]
-impl ClientExtensions for RpcClient {
-    async fn transaction_from_instructions(
-        &mut self,
-        ixs: &[Instruction],
-        payer: &Keypair,
-        signers: Vec<&Keypair>,
-    ) -> Result<Transaction, Box<dyn std::error::Error>> {
-        let latest_blockhash = self.get_latest_blockhash()?;
-
-        Ok(Transaction::new_signed_with_payer(
-            ixs,
-            Some(&payer.pubkey()),
-            &signers,
-            latest_blockhash,
-        ))
-    }
-
-    #[cfg(feature = "anchor")]
-    async fn get_account_with_anchor<T: AccountDeserialize>(
-        &mut self,
-        address: Pubkey,
-    ) -> Result<T, Box<dyn std::error::Error>> {
-        self.get_account_data(&address).map(|account_data| {
-            T::try_deserialize(&mut account_data.as_ref()).map_err(Into::into)
-        })?
-    }
-
-    async fn get_account_with_borsh<T: BorshDeserialize>(
-        &mut self,
-        address: Pubkey,
-    ) -> Result<T, Box<dyn std::error::Error>> {
-        self.get_account_data(&address)
-            .map(|account_data| T::deserialize(&mut account_data.as_ref()).map_err(Into::into))?
-    }
-
-    #[cfg(feature = "pyth")]
-    async fn get_pyth_price_account(
-        &mut self,
-        address: Pubkey,
-    ) -> Result<PriceAccount, Box<dyn std::error::Error>> {
-        self.get_account_data(&address).map(|account_data| {
-            //PriceFeed::deserialize(&mut account_data.as_ref()).map_err(Into::into)
-            let data = account_data;
-            let price_account =
-                pyth_sdk_solana::state::load_price_account(&data).map_err(|_| {
-                    BanksClientError::ClientError("Failed to deserialize price account")
-                })?;
-            Ok(*price_account)
-        })?
-    }
-
-    async fn create_account(
-        &mut self,
-        from: &Keypair,
-        to: &Keypair,
-        lamports: u64,
-        space: u64,
-        owner: Pubkey,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let latest_blockhash = self.get_latest_blockhash()?;
-
-        self.send_and_confirm_transaction(&system_transaction::create_account(
-            from,
-            to,
-            latest_blockhash,
-            lamports,
-            space,
-            &owner,
-        ))
-        .map(|_| ())
-        .map_err(Into::into)
-    }
-
-    async fn create_token_mint(
-        &mut self,
-        mint: &Keypair,
-        authority: &Pubkey,
-        freeze_authority: Option<&Pubkey>,
-        decimals: u8,
-        payer: &Keypair,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let latest_blockhash = self.get_latest_blockhash()?;
-        self.send_and_confirm_transaction(&system_transaction::create_account(
-            payer,
-            mint,
-            latest_blockhash,
-            Rent::default().minimum_balance(spl_token::state::Mint::get_packed_len()),
-            spl_token::state::Mint::get_packed_len() as u64,
-            &spl_token::id(),
-        ))?;
-
-        let tx = self
-            .transaction_from_instructions(
-                &[spl_token::instruction::initialize_mint(
-                    &spl_token::id(),
-                    &mint.pubkey(),
+/// Number of program write transactions in flight at once
+const MAX_CONCURRENT_PROGRAM_WRITES: usize = 16;
+/// Number of rounds of sending the program chunks missing from the account
+const MAX_PROGRAM_WRITE_ATTEMPTS: usize = 5;
+/// Number of transactions of a batch in flight at once
+const MAX_CONCURRENT_TRANSACTIONS: usize = 64;
+
+/// Implement `ClientExtensions` for an `RpcClient` backend with the functions of this module,
+/// which only talk to the node through `PolicyClient`.
+macro_rules! rpc_client_extensions {
+    ($client:ty) => {
+        #[async_trait]
+        impl ClientExtensions for $client {
+            async fn transaction_from_instructions(
+                &mut self,
+                ixs: &[Instruction],
+                payer: &Keypair,
+                signers: Vec<&Keypair>,
+            ) -> Result<Transaction, TestFrameWorkError> {
+                transaction_from_instructions(self, ixs, payer, &signers).await
+            }
+
+            async fn process_instructions(
+                &mut self,
+                ixs: &[Instruction],
+                payer: &Keypair,
+                signers: &[&Keypair],
+            ) -> Result<(), TestFrameWorkError> {
+                process_instructions(self, ixs, payer, signers).await
+            }
+
+            async fn build_message(
+                &mut self,
+                builder: &TransactionBuilder,
+            ) -> Result<Message, TestFrameWorkError> {
+                build_message(self, builder).await
+            }
+
+            async fn build_versioned_message(
+                &mut self,
+                builder: &TransactionBuilder,
+            ) -> Result<VersionedMessage, TestFrameWorkError> {
+                build_versioned_message(self, builder).await
+            }
+
+            async fn process_versioned_transaction(
+                &mut self,
+                transaction: VersionedTransaction,
+            ) -> Result<(), TestFrameWorkError> {
+                send_and_confirm(self, PolicyTransaction::Signed(transaction))
+                    .await
+                    .map(|_| ())
+            }
+
+            async fn send_transactions(
+                &mut self,
+                transactions: Vec<Transaction>,
+                mode: SendMode,
+            ) -> Result<BatchOutcome, TestFrameWorkError> {
+                send_transactions(self, transactions, mode).await
+            }
+
+            async fn simulate_instructions(
+                &mut self,
+                ixs: &[Instruction],
+                payer: &Keypair,
+                signers: Vec<&Keypair>,
+                post_accounts: &[Pubkey],
+            ) -> Result<SimulationOutcome, TestFrameWorkError> {
+                simulate_instructions(self, ixs, payer, &signers, post_accounts).await
+            }
+
+            #[cfg(feature = "anchor")]
+            async fn get_account_with_anchor<T: AccountDeserialize>(
+                &mut self,
+                address: Pubkey,
+            ) -> Result<T, TestFrameWorkError> {
+                let account_data = get_account_data(self, address).await?;
+
+                T::try_deserialize(&mut account_data.as_ref())
+                    .map_err(|_| TestFrameWorkError::deserialize::<T>(address))
+            }
+
+            #[cfg(feature = "anchor")]
+            async fn get_anchor_events<T: Event + Send, S: Into<EventSource> + Send>(
+                &mut self,
+                source: S,
+            ) -> Result<Vec<T>, TestFrameWorkError> {
+                get_anchor_events(self, source.into()).await
+            }
+
+            #[cfg(feature = "idl")]
+            async fn get_account_as_json(
+                &mut self,
+                address: Pubkey,
+                idl: &AnchorIdl,
+            ) -> Result<Value, TestFrameWorkError> {
+                let data = get_account_data(self, address).await?;
+                Ok(idl.decode_account(&data)?.1)
+            }
+
+            async fn get_account_with_borsh<T: BorshDeserialize>(
+                &mut self,
+                address: Pubkey,
+            ) -> Result<T, TestFrameWorkError> {
+                let account_data = get_account_data(self, address).await?;
+
+                T::deserialize(&mut account_data.as_ref())
+                    .map_err(|_| TestFrameWorkError::deserialize::<T>(address))
+            }
+
+            async fn get_account_with_pack<P: Pack + IsInitialized>(
+                &mut self,
+                address: Pubkey,
+            ) -> Result<P, TestFrameWorkError> {
+                let account_data = get_account_data(self, address).await?;
+
+                P::unpack(&account_data)
+                    .map_err(|_| TestFrameWorkError::deserialize::<P>(address))
+            }
+
+            async fn get_program_accounts(
+                &mut self,
+                program_id: &Pubkey,
+                filters: Vec<RpcFilterType>,
+            ) -> Result<Vec<(Pubkey, Account)>, TestFrameWorkError> {
+                let config =
+                    rpc_program_accounts_config(filters, rpc_commitment(self.client_commitment()));
+
+                PolicyClient::program_accounts(self, program_id, config).await
+            }
+
+            async fn get_program_info(
+                &mut self,
+                program_id: &Pubkey,
+            ) -> Result<UpgradeableProgramInfo, TestFrameWorkError> {
+                get_program_info(self, program_id).await
+            }
+
+            #[cfg(feature = "loader-v4")]
+            async fn get_program_v4_state(
+                &mut self,
+                program_id: &Pubkey,
+            ) -> Result<LoaderV4State, TestFrameWorkError> {
+                loader_v4_state(*program_id, &get_existing_account(self, *program_id).await?)
+            }
+
+            async fn get_mint(
+                &mut self,
+                address: Pubkey,
+            ) -> Result<TokenState<Mint>, TestFrameWorkError> {
+                unpack_token_state(address, get_existing_account(self, address).await?)
+            }
+
+            async fn get_token_account(
+                &mut self,
+                address: Pubkey,
+            ) -> Result<TokenState<TokenAccount>, TestFrameWorkError> {
+                unpack_token_state(address, get_existing_account(self, address).await?)
+            }
+
+            #[cfg(feature = "pyth")]
+            async fn get_pyth_price_account(
+                &mut self,
+                address: Pubkey,
+            ) -> Result<PriceAccount, TestFrameWorkError> {
+                let account_data = get_account_data(self, address).await?;
+
+                let price_account = pyth_sdk_solana::state::load_price_account(&account_data)
+                    .map_err(|_| TestFrameWorkError::deserialize::<PriceAccount>(address))?;
+                Ok(*price_account)
+            }
+
+            async fn create_account(
+                &mut self,
+                from: &Keypair,
+                to: &Keypair,
+                lamports: u64,
+                space: u64,
+                owner: Pubkey,
+            ) -> Result<(), TestFrameWorkError> {
+                create_account(self, from, to, lamports, space, &owner).await
+            }
+
+            async fn create_token_mint(
+                &mut self,
+                mint: &Keypair,
+                authority: &Pubkey,
+                freeze_authority: Option<&Pubkey>,
+                decimals: u8,
+                token_program_id: &Pubkey,
+                payer: &Keypair,
+            ) -> Result<(), TestFrameWorkError> {
+                create_token_mint(
+                    self,
+                    mint,
                     authority,
                     freeze_authority,
                     decimals,
-                )?],
-                payer,
-                vec![payer],
-            )
-            .await?;
-
-        self.send_and_confirm_transaction(&tx)
-            .map(|_| ())
-            .map_err(Into::into)
-    }
-
-    async fn create_token_account(
-        &mut self,
-        account: &Keypair,
-        authority: &Pubkey,
-        mint: &Pubkey,
-        payer: &Keypair,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let latest_blockhash = self.get_latest_blockhash()?;
-
-        self.send_and_confirm_transaction(&system_transaction::create_account(
-            payer,
-            account,
-            latest_blockhash,
-            Rent::default().minimum_balance(spl_token::state::Account::get_packed_len()),
-            spl_token::state::Account::get_packed_len() as u64,
-            &spl_token::id(),
-        ))?;
-
-        let tx = self
-            .transaction_from_instructions(
-                &[spl_token::instruction::initialize_account(
-                    &spl_token::id(),
-                    &account.pubkey(),
-                    mint,
-                    authority,
-                )?],
-                payer,
-                vec![payer],
-            )
-            .await?;
-
-        self.send_and_confirm_transaction(&tx)
-            .map(|_| ())
-            .map_err(Into::into)
-    }
-
-    async fn create_associated_token_account(
-        &mut self,
-        account: &Pubkey,
-        mint: &Pubkey,
-        payer: &Keypair,
-        token_program_id: &Pubkey,
-    ) -> Result<Pubkey, Box<dyn std::error::Error>> {
-        let associated_token_account = get_associated_token_address(account, mint);
-
-        let tx = self
-            .transaction_from_instructions(
-                &[create_associated_token_account_ix(
+                    token_program_id,
+                    payer,
+                )
+                .await
+            }
+
+            async fn create_token_account(
+                &mut self,
+                account: &Keypair,
+                authority: &Pubkey,
+                mint: &Pubkey,
+                token_program_id: &Pubkey,
+                payer: &Keypair,
+            ) -> Result<(), TestFrameWorkError> {
+                create_token_account(self, account, authority, mint, token_program_id, payer).await
+            }
+
+            async fn create_associated_token_account(
+                &mut self,
+                account: &Pubkey,
+                mint: &Pubkey,
+                payer: &Keypair,
+                token_program_id: &Pubkey,
+            ) -> Result<Pubkey, TestFrameWorkError> {
+                let associated_token_account =
+                    get_associated_token_address_with_program_id(account, mint, token_program_id);
+                let ix = create_associated_token_account_ix(
                     &payer.pubkey(),
                     account,
                     mint,
                     token_program_id,
-                )],
-                payer,
-                vec![payer],
-            )
-            .await?;
-
-        self.send_and_confirm_transaction(&tx)
-            .map(|_| associated_token_account)
-            .map_err(Into::into)
+                );
+
+                process_instructions(self, &[ix], payer, &[])
+                    .await
+                    .map(|_| associated_token_account)
+            }
+
+            async fn write_program_data(
+                &mut self,
+                account: &Pubkey,
+                data_offset: usize,
+                program_data: &[u8],
+                write_ix: &(dyn Fn(u32, Vec<u8>) -> Instruction + Sync),
+                payer: &Keypair,
+                write_authority: &Keypair,
+            ) -> Result<(), TestFrameWorkError> {
+                write_program_data(
+                    self,
+                    account,
+                    data_offset,
+                    program_data,
+                    write_ix,
+                    payer,
+                    write_authority,
+                )
+                .await
+            }
+        }
+    };
+}
+
+rpc_client_extensions!(solana_client::rpc_client::RpcClient);
+rpc_client_extensions!(solana_client::nonblocking::rpc_client::RpcClient);
+
+/// Number of requests of a client in flight at once, the blocking client sends them one by one.
+fn max_concurrent<C: PolicyClient>(limit: usize) -> usize {
+    if C::BLOCKING {
+        1
+    } else {
+        limit
     }
+}
 
-    async fn deploy_program(
-        &mut self,
-        path_to_program: &str,
-        program_keypair: &Keypair,
-        payer: &Keypair,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let (buffer, buffer_len) = util::load_file_to_bytes(path_to_program);
-
-        let program_data = buffer;
-
-        // multiply by 2 so program can be updated later on
-        let program_len = buffer_len;
-        let minimum_balance = Rent::default().minimum_balance(
-            bpf_loader_upgradeable::UpgradeableLoaderState::programdata_len(program_len)
-                .expect("Cannot get program len"),
-        );
-        let latest_blockhash = self.get_latest_blockhash()?;
-
-        // 1 Create account
-        self.send_and_confirm_transaction(&system_transaction::create_account(
-            payer,
-            program_keypair,
-            latest_blockhash,
-            minimum_balance,
-            program_len as u64,
-            &bpf_loader::id(),
-        ))?;
-
-        // 2. Write to buffer
-        let deploy_ix = |offset: u32, bytes: Vec<u8>| {
-            loader_instruction::write(&program_keypair.pubkey(), &bpf_loader::id(), offset, bytes)
-        };
-
-        let chunk_size = util::calculate_chunk_size(deploy_ix, &vec![payer, program_keypair]);
-
-        for (chunk, i) in program_data.chunks(chunk_size).zip(0..) {
-            let ix = deploy_ix(i * chunk_size as u32, chunk.to_vec());
-            let tx = self
-                .transaction_from_instructions(&[ix], payer, vec![payer, program_keypair])
-                .await
-                .unwrap();
+async fn transaction_from_instructions<C: PolicyClient>(
+    client: &C,
+    ixs: &[Instruction],
+    payer: &Keypair,
+    signers: &[&Keypair],
+) -> Result<Transaction, TestFrameWorkError> {
+    let latest_blockhash = latest_blockhash(client).await?;
 
-            self.send_and_confirm_transaction(&tx)?;
-        }
+    signed_transaction(ixs, payer, signers, latest_blockhash)
+}
 
-        // 3. Finalize
-        // let finalize_msg = Message::new_with_blockhash(
-        //     &[loader_instruction::finalize(
-        //         &program_keypair.pubkey(),
-        //         &bpf_loader::id(),
-        //     )],
-        //     Some(&payer.pubkey()),
-        //     &latest_blockhash,
-        // );
-        // let finalize_tx = Transaction::new(&[payer, program_keypair], finalize_msg, latest_blockhash);
-
-        let finalize_tx = self
-            .transaction_from_instructions(
-                &[loader_instruction::finalize(
-                    &program_keypair.pubkey(),
-                    &bpf_loader::id(),
-                )],
-                payer,
-                vec![payer, program_keypair],
-            )
-            .await
-            .unwrap();
+async fn process_instructions<C: PolicyClient>(
+    client: &C,
+    ixs: &[Instruction],
+    payer: &Keypair,
+    signers: &[&Keypair],
+) -> Result<(), TestFrameWorkError> {
+    send_and_confirm(
+        client,
+        PolicyTransaction::Resignable(&|latest_blockhash| {
+            signed_transaction(ixs, payer, signers, latest_blockhash)
+        }),
+    )
+    .await
+    .map(|_| ())
+}
+
+async fn build_message<C: PolicyClient>(
+    client: &C,
+    builder: &TransactionBuilder,
+) -> Result<Message, TestFrameWorkError> {
+    let recent_blockhash = resolve_recent_blockhash(client, builder.recent_blockhash).await?;
+
+    Ok(builder.message(recent_blockhash))
+}
+
+async fn build_versioned_message<C: PolicyClient>(
+    client: &C,
+    builder: &TransactionBuilder,
+) -> Result<VersionedMessage, TestFrameWorkError> {
+    let recent_blockhash = resolve_recent_blockhash(client, builder.recent_blockhash).await?;
 
-        self.send_and_confirm_transaction(&finalize_tx)
-            .map(|_| ())
-            .map_err(Into::into)
+    let mut address_lookup_tables = Vec::new();
+    for address in &builder.address_lookup_tables {
+        let data = get_account_data(client, *address).await?;
+        address_lookup_tables.push(lookup_table_account(*address, &data)?);
     }
 
-    async fn deploy_upgradable_program(
-        &mut self,
-        path_to_program: &str,
-        buffer_keypair: &Keypair,
-        buffer_authority_signer: &Keypair,
-        program_keypair: &Keypair,
-        payer: &Keypair,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let (buffer, buffer_len) = util::load_file_to_bytes(path_to_program);
-
-        let program_data = buffer;
-
-        // multiply by 2 so program can be updated later on
-        let program_len = buffer_len * 2;
-        let minimum_balance = Rent::default().minimum_balance(
-            bpf_loader_upgradeable::UpgradeableLoaderState::programdata_len(program_len)
-                .expect("Cannot get program len"),
-        );
-
-        // 1 Create buffer
-        let create_buffer_ix = bpf_loader_upgradeable::create_buffer(
-            &payer.pubkey(),
-            &buffer_keypair.pubkey(),
-            &buffer_authority_signer.pubkey(),
-            minimum_balance,
-            program_len,
+    builder.versioned_message(recent_blockhash, &address_lookup_tables)
+}
+
+async fn send_transactions<C: PolicyClient>(
+    client: &C,
+    transactions: Vec<Transaction>,
+    mode: SendMode,
+) -> Result<BatchOutcome, TestFrameWorkError> {
+    let policy = &batch_send_policy(client.client_commitment());
+
+    send_in_waves(transactions, &mode, |wave| async move {
+        // buffered keeps the results in the order of the wave
+        stream::iter(wave)
+            .map(|transaction| send_with_outcome(client, transaction, policy))
+            .buffered(max_concurrent::<C>(MAX_CONCURRENT_TRANSACTIONS))
+            .collect()
+            .await
+    })
+    .await
+}
+
+async fn simulate_instructions<C: PolicyClient>(
+    client: &C,
+    ixs: &[Instruction],
+    payer: &Keypair,
+    signers: &[&Keypair],
+    post_accounts: &[Pubkey],
+) -> Result<SimulationOutcome, TestFrameWorkError> {
+    let tx = transaction_from_instructions(client, ixs, payer, signers).await?;
+    let simulation = client
+        .simulate_transaction(
+            &tx,
+            rpc_simulate_config(post_accounts, rpc_commitment(client.client_commitment())),
         )
-        .expect("Cannot create buffer");
-
-        let mut tx = self
-            .transaction_from_instructions(
-                create_buffer_ix.as_ref(),
-                payer,
-                vec![payer, buffer_keypair],
-            )
-            .await?;
-
-        self.send_and_confirm_transaction(&tx)?;
-
-        // 2 Write to buffer
-        let deploy_ix = |offset: u32, bytes: Vec<u8>| {
-            bpf_loader_upgradeable::write(
-                &buffer_keypair.pubkey(),
-                &buffer_authority_signer.pubkey(),
-                offset,
-                bytes,
-            )
-        };
-
-        let chunk_size =
-            util::calculate_chunk_size(deploy_ix, &vec![payer, buffer_authority_signer]);
-
-        for (chunk, i) in program_data.chunks(chunk_size).zip(0..) {
-            let ix = deploy_ix(i * chunk_size as u32, chunk.to_vec());
-
-            tx = self
-                .transaction_from_instructions(&[ix], payer, vec![payer, buffer_authority_signer])
-                .await
-                .unwrap();
+        .await?;
+
+    simulation_outcome_from_rpc(simulation)
+}
 
-            self.send_and_confirm_transaction(&tx)?;
+#[cfg(feature = "anchor")]
+async fn get_anchor_events<C: PolicyClient, T: Event>(
+    client: &C,
+    source: EventSource,
+) -> Result<Vec<T>, TestFrameWorkError> {
+    match source {
+        EventSource::Logs(logs) => parse_anchor_events(&logs),
+        EventSource::Signature(signature) => {
+            let transaction = client
+                .executed_transaction(&signature, rpc_commitment(client.client_commitment()))
+                .await?;
+
+            events_from_confirmed_transaction(transaction)
         }
+    }
+}
 
-        // 3. Finalize
-        tx = self
-            .transaction_from_instructions(
-                bpf_loader_upgradeable::deploy_with_max_program_len(
-                    &payer.pubkey(),
-                    &program_keypair.pubkey(),
-                    &buffer_keypair.pubkey(),
-                    &buffer_authority_signer.pubkey(),
-                    minimum_balance,
-                    program_len,
-                )
-                .expect("Cannot parse deploy instruction")
-                .as_ref(),
-                payer,
-                vec![payer, program_keypair, buffer_authority_signer],
-            )
-            .await?;
-
-        self.send_and_confirm_transaction(&tx)
-            .map(|_| ())
-            .map_err(Into::into)
+async fn get_program_info<C: PolicyClient>(
+    client: &C,
+    program_id: &Pubkey,
+) -> Result<UpgradeableProgramInfo, TestFrameWorkError> {
+    let program = get_existing_account(client, *program_id).await?;
+    let programdata_address = programdata_address(*program_id, &program)?;
+    let programdata = get_existing_account(client, programdata_address).await?;
+
+    upgradeable_program_info(programdata_address, &programdata)
+}
+
+async fn create_account<C: PolicyClient>(
+    client: &C,
+    from: &Keypair,
+    to: &Keypair,
+    lamports: u64,
+    space: u64,
+    owner: &Pubkey,
+) -> Result<(), TestFrameWorkError> {
+    let ix =
+        system_instruction::create_account(&from.pubkey(), &to.pubkey(), lamports, space, owner);
+
+    process_instructions(client, &[ix], from, &[to]).await
+}
+
+async fn create_token_mint<C: PolicyClient>(
+    client: &C,
+    mint: &Keypair,
+    authority: &Pubkey,
+    freeze_authority: Option<&Pubkey>,
+    decimals: u8,
+    token_program_id: &Pubkey,
+    payer: &Keypair,
+) -> Result<(), TestFrameWorkError> {
+    create_account(
+        client,
+        payer,
+        mint,
+        Rent::default().minimum_balance(spl_token::state::Mint::get_packed_len()),
+        spl_token::state::Mint::get_packed_len() as u64,
+        token_program_id,
+    )
+    .await?;
+
+    let ix = token_instruction::initialize_mint(
+        token_program_id,
+        &mint.pubkey(),
+        authority,
+        freeze_authority,
+        decimals,
+    )?;
+
+    process_instructions(client, &[ix], payer, &[]).await
+}
+
+async fn create_token_account<C: PolicyClient>(
+    client: &C,
+    account: &Keypair,
+    authority: &Pubkey,
+    mint: &Pubkey,
+    token_program_id: &Pubkey,
+    payer: &Keypair,
+) -> Result<(), TestFrameWorkError> {
+    create_account(
+        client,
+        payer,
+        account,
+        Rent::default().minimum_balance(spl_token::state::Account::get_packed_len()),
+        spl_token::state::Account::get_packed_len() as u64,
+        token_program_id,
+    )
+    .await?;
+
+    let ix = token_instruction::initialize_account(
+        token_program_id,
+        &account.pubkey(),
+        mint,
+        authority,
+    )?;
+
+    process_instructions(client, &[ix], payer, &[]).await
+}
+
+/// Write the program data in chunks, retrying the chunks missing from the account.
+async fn write_program_data<C: PolicyClient>(
+    client: &C,
+    account: &Pubkey,
+    data_offset: usize,
+    program_data: &[u8],
+    write_ix: &(dyn Fn(u32, Vec<u8>) -> Instruction + Sync),
+    payer: &Keypair,
+    write_authority: &Keypair,
+) -> Result<(), TestFrameWorkError> {
+    let chunk_size = util::calculate_chunk_size(write_ix, &vec![payer, write_authority]);
+    let chunks: Vec<&[u8]> = program_data.chunks(chunk_size).collect();
+    let mut pending: Vec<usize> = (0..chunks.len()).collect();
+    let mut last_error = None;
+
+    for _ in 0..MAX_PROGRAM_WRITE_ATTEMPTS {
+        // a fresh blockhash makes the chunks sent again new transactions
+        let latest_blockhash = latest_blockhash(client).await?;
+        let chunks = &chunks;
+        let results: Vec<Result<(), TestFrameWorkError>> = stream::iter(pending.clone())
+            .map(|i| async move {
+                let ix = write_ix((i * chunk_size) as u32, chunks[i].to_vec());
+                let transaction =
+                    signed_transaction(&[ix], payer, &[write_authority], latest_blockhash)?;
+
+                send_and_confirm(client, PolicyTransaction::Signed(transaction.into()))
+                    .await
+                    .map(|_| ())
+            })
+            .buffer_unordered(max_concurrent::<C>(MAX_CONCURRENT_PROGRAM_WRITES))
+            .collect()
+            .await;
+
+        for error in results.into_iter().filter_map(Result::err) {
+            // an instruction error fails the same way again, only retry transient failures
+            if let TestFrameWorkError::Transaction {
+                error: TransactionError::InstructionError(..),
+                ..
+            } = error
+            {
+                return Err(error);
+            }
+            last_error = Some(error);
+        }
+
+        // a write can land even though its confirmation failed, so the account is checked for the missing chunks
+        let data = get_account_data(client, *account).await?;
+        pending.retain(|&i| {
+            let start = data_offset + i * chunk_size;
+            data.get(start..start + chunks[i].len()) != Some(chunks[i])
+        });
+        if pending.is_empty() {
+            return Ok(());
+        }
+    }
+
+    Err(last_error.unwrap_or(TestFrameWorkError::Error(
+        "Program data was not fully written to the account",
+    )))
+}
+
+/// Fetch the data of an account, failing with `AccountNotFound` if it does not exist.
+async fn get_account_data<C: PolicyClient>(
+    client: &C,
+    address: Pubkey,
+) -> Result<Vec<u8>, TestFrameWorkError> {
+    Ok(get_existing_account(client, address).await?.data)
+}
+
+/// Fetch an account, failing if it does not exist.
+async fn get_existing_account<C: PolicyClient>(
+    client: &C,
+    address: Pubkey,
+) -> Result<Account, TestFrameWorkError> {
+    client
+        .account(&address, rpc_commitment(client.client_commitment()))
+        .await?
+        .ok_or(TestFrameWorkError::AccountNotFound(address))
+}
+
+/// Resolve the recent blockhash of a transaction builder.
+async fn resolve_recent_blockhash<C: PolicyClient>(
+    client: &C,
+    recent_blockhash: RecentBlockhash,
+) -> Result<Hash, TestFrameWorkError> {
+    match recent_blockhash {
+        RecentBlockhash::Latest => latest_blockhash(client).await,
+        RecentBlockhash::Hash(hash) => Ok(hash),
+        RecentBlockhash::Nonce { account, .. } => {
+            nonce_blockhash(account, &get_account_data(client, account).await?)
+        }
     }
 }
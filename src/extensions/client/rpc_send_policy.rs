@@ -0,0 +1,802 @@
+use super::*;
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{RpcProgramAccountsConfig, RpcSendTransactionConfig},
+};
+use solana_program_test::tokio;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use std::{
+    future::Future,
+    ops::{Deref, DerefMut},
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "pyth")]
+use pyth_sdk_solana::state::PriceAccount;
+
+/// How the `RpcClient` backends send and confirm transactions and at which commitment they read,
+/// attached to a client with `RpcClientWithPolicy` or set for a single call with `with_rpc_send_policy`.
+/// `BanksClient` processes transactions synchronously and ignores it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcSendPolicy {
+    pub(crate) commitment: Option<CommitmentConfig>,
+    pub(crate) max_retries: usize,
+    pub(crate) resign_expired: bool,
+    pub(crate) confirmation_timeout: Duration,
+    pub(crate) poll_interval: Duration,
+}
+
+impl Default for RpcSendPolicy {
+    fn default() -> Self {
+        Self {
+            commitment: None,
+            max_retries: 3,
+            resign_expired: true,
+            confirmation_timeout: Duration::from_secs(60),
+            poll_interval: Duration::from_millis(400),
+        }
+    }
+}
+
+impl RpcSendPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Confirm transactions and read accounts at this commitment instead of the commitment of the client.
+    pub fn commitment(mut self, commitment: CommitmentConfig) -> Self {
+        self.commitment = Some(commitment);
+        self
+    }
+
+    /// Send a transaction at most this many more times after `BlockhashNotFound` or an expired blockhash.
+    pub fn max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Re-sign transactions with a new blockhash when theirs expired before they were confirmed.
+    /// Transactions that were signed before they were passed to the client are never re-signed.
+    pub fn resign_expired(mut self, resign_expired: bool) -> Self {
+        self.resign_expired = resign_expired;
+        self
+    }
+
+    /// Give up waiting for the confirmation of a sent transaction after this long.
+    pub fn confirmation_timeout(mut self, timeout: Duration) -> Self {
+        self.confirmation_timeout = timeout;
+        self
+    }
+
+    /// Poll the status of a sent transaction at this interval.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// The commitment of the policy, or the commitment of the client if none is set.
+    pub(crate) fn commitment_or(&self, client_commitment: CommitmentConfig) -> CommitmentConfig {
+        self.commitment.unwrap_or(client_commitment)
+    }
+}
+
+tokio::task_local! {
+    static CALL_POLICY: RpcSendPolicy;
+    static CLIENT_POLICY: RpcSendPolicy;
+}
+
+/// Run the calls of a future with the given policy, e.g. to confirm one call at another commitment.
+/// It overrides the policy of an `RpcClientWithPolicy` for these calls.
+pub async fn with_rpc_send_policy<F: Future>(policy: RpcSendPolicy, future: F) -> F::Output {
+    CALL_POLICY.scope(policy, future).await
+}
+
+/// The policy of the current call: the policy of `with_rpc_send_policy` if there is one,
+/// otherwise the policy of the `RpcClientWithPolicy` making the call, otherwise the default policy.
+pub(crate) fn rpc_send_policy() -> RpcSendPolicy {
+    CALL_POLICY
+        .try_with(Clone::clone)
+        .or_else(|_| CLIENT_POLICY.try_with(Clone::clone))
+        .unwrap_or_default()
+}
+
+/// The commitment the `RpcClient` backends read and confirm at in the current call.
+pub(crate) fn rpc_commitment(client_commitment: CommitmentConfig) -> CommitmentConfig {
+    rpc_send_policy().commitment_or(client_commitment)
+}
+
+/// A transaction sent according to the policy.
+pub(crate) enum PolicyTransaction<'a> {
+    /// A transaction signed by the caller, sent again as is
+    Signed(VersionedTransaction),
+    /// A transaction signed with the given blockhash, signed again with a new blockhash on a retry
+    Resignable(&'a (dyn Fn(Hash) -> Result<Transaction, TestFrameWorkError> + Sync)),
+}
+
+impl PolicyTransaction<'_> {
+    /// Whether sending the transaction again can succeed after the error. Transactions are only sent again
+    /// when they certainly did not land, so a timed out confirmation is not retried.
+    pub(crate) fn is_retryable(&self, policy: &RpcSendPolicy, error: &TestFrameWorkError) -> bool {
+        match error {
+            TestFrameWorkError::Transaction {
+                error: TransactionError::BlockhashNotFound,
+                ..
+            } => true,
+            TestFrameWorkError::BlockhashExpired(_) => {
+                policy.resign_expired && matches!(self, Self::Resignable(_))
+            }
+            _ => false,
+        }
+    }
+}
+
+/// An `RpcClient` or nonblocking `RpcClient` with the `RpcSendPolicy` its `ClientExtensions` calls use.
+/// It dereferences to the client for the calls outside of the extensions.
+pub struct RpcClientWithPolicy<C> {
+    client: C,
+    policy: RpcSendPolicy,
+}
+
+impl<C> RpcClientWithPolicy<C> {
+    pub fn new(client: C, policy: RpcSendPolicy) -> Self {
+        Self { client, policy }
+    }
+
+    pub fn policy(&self) -> &RpcSendPolicy {
+        &self.policy
+    }
+
+    /// Replace the policy of the following calls.
+    pub fn set_policy(&mut self, policy: RpcSendPolicy) {
+        self.policy = policy;
+    }
+
+    /// Return the underlying client.
+    pub fn into_inner(self) -> C {
+        self.client
+    }
+}
+
+impl<C> Deref for RpcClientWithPolicy<C> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        &self.client
+    }
+}
+
+impl<C> DerefMut for RpcClientWithPolicy<C> {
+    fn deref_mut(&mut self) -> &mut C {
+        &mut self.client
+    }
+}
+
+/// Run a call of the wrapped client with the policy of the wrapper.
+async fn with_client_policy<F: Future>(policy: &RpcSendPolicy, future: F) -> F::Output {
+    CLIENT_POLICY.scope(policy.clone(), future).await
+}
+
+/// Every method the `RpcClient` backends implement is run with the policy of the wrapper,
+/// the other methods are built on them.
+#[async_trait]
+impl<C: ClientExtensions + Send> ClientExtensions for RpcClientWithPolicy<C> {
+    async fn transaction_from_instructions(
+        &mut self,
+        ixs: &[Instruction],
+        payer: &Keypair,
+        signers: Vec<&Keypair>,
+    ) -> Result<Transaction, TestFrameWorkError> {
+        with_client_policy(
+            &self.policy,
+            self.client
+                .transaction_from_instructions(ixs, payer, signers),
+        )
+        .await
+    }
+
+    async fn simulate_instructions(
+        &mut self,
+        ixs: &[Instruction],
+        payer: &Keypair,
+        signers: Vec<&Keypair>,
+        post_accounts: &[Pubkey],
+    ) -> Result<SimulationOutcome, TestFrameWorkError> {
+        with_client_policy(
+            &self.policy,
+            self.client
+                .simulate_instructions(ixs, payer, signers, post_accounts),
+        )
+        .await
+    }
+
+    async fn build_message(
+        &mut self,
+        builder: &TransactionBuilder,
+    ) -> Result<Message, TestFrameWorkError> {
+        with_client_policy(&self.policy, self.client.build_message(builder)).await
+    }
+
+    async fn build_versioned_message(
+        &mut self,
+        builder: &TransactionBuilder,
+    ) -> Result<VersionedMessage, TestFrameWorkError> {
+        with_client_policy(&self.policy, self.client.build_versioned_message(builder)).await
+    }
+
+    async fn process_versioned_transaction(
+        &mut self,
+        transaction: VersionedTransaction,
+    ) -> Result<(), TestFrameWorkError> {
+        with_client_policy(
+            &self.policy,
+            self.client.process_versioned_transaction(transaction),
+        )
+        .await
+    }
+
+    #[cfg(feature = "anchor")]
+    async fn get_account_with_anchor<T: AccountDeserialize>(
+        &mut self,
+        address: Pubkey,
+    ) -> Result<T, TestFrameWorkError> {
+        with_client_policy(&self.policy, self.client.get_account_with_anchor(address)).await
+    }
+
+    #[cfg(feature = "anchor")]
+    async fn get_anchor_events<T: Event + Send, S: Into<EventSource> + Send>(
+        &mut self,
+        source: S,
+    ) -> Result<Vec<T>, TestFrameWorkError> {
+        with_client_policy(&self.policy, self.client.get_anchor_events(source)).await
+    }
+
+    #[cfg(feature = "idl")]
+    async fn get_account_as_json(
+        &mut self,
+        address: Pubkey,
+        idl: &AnchorIdl,
+    ) -> Result<Value, TestFrameWorkError> {
+        with_client_policy(&self.policy, self.client.get_account_as_json(address, idl)).await
+    }
+
+    async fn get_account_with_borsh<T: BorshDeserialize>(
+        &mut self,
+        address: Pubkey,
+    ) -> Result<T, TestFrameWorkError> {
+        with_client_policy(&self.policy, self.client.get_account_with_borsh(address)).await
+    }
+
+    async fn get_account_with_pack<P: Pack + IsInitialized>(
+        &mut self,
+        address: Pubkey,
+    ) -> Result<P, TestFrameWorkError> {
+        with_client_policy(&self.policy, self.client.get_account_with_pack(address)).await
+    }
+
+    async fn get_program_accounts(
+        &mut self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>, TestFrameWorkError> {
+        with_client_policy(
+            &self.policy,
+            self.client.get_program_accounts(program_id, filters),
+        )
+        .await
+    }
+
+    async fn get_mint(&mut self, address: Pubkey) -> Result<TokenState<Mint>, TestFrameWorkError> {
+        with_client_policy(&self.policy, self.client.get_mint(address)).await
+    }
+
+    async fn get_token_account(
+        &mut self,
+        address: Pubkey,
+    ) -> Result<TokenState<TokenAccount>, TestFrameWorkError> {
+        with_client_policy(&self.policy, self.client.get_token_account(address)).await
+    }
+
+    #[cfg(feature = "pyth")]
+    async fn get_pyth_price_account(
+        &mut self,
+        address: Pubkey,
+    ) -> Result<PriceAccount, TestFrameWorkError> {
+        with_client_policy(&self.policy, self.client.get_pyth_price_account(address)).await
+    }
+
+    async fn create_account(
+        &mut self,
+        from: &Keypair,
+        to: &Keypair,
+        lamports: u64,
+        space: u64,
+        owner: Pubkey,
+    ) -> Result<(), TestFrameWorkError> {
+        with_client_policy(
+            &self.policy,
+            self.client.create_account(from, to, lamports, space, owner),
+        )
+        .await
+    }
+
+    async fn create_token_mint(
+        &mut self,
+        mint: &Keypair,
+        authority: &Pubkey,
+        freeze_authority: Option<&Pubkey>,
+        decimals: u8,
+        token_program_id: &Pubkey,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        with_client_policy(
+            &self.policy,
+            self.client.create_token_mint(
+                mint,
+                authority,
+                freeze_authority,
+                decimals,
+                token_program_id,
+                payer,
+            ),
+        )
+        .await
+    }
+
+    async fn create_token_account(
+        &mut self,
+        account: &Keypair,
+        authority: &Pubkey,
+        mint: &Pubkey,
+        token_program_id: &Pubkey,
+        payer: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        with_client_policy(
+            &self.policy,
+            self.client
+                .create_token_account(account, authority, mint, token_program_id, payer),
+        )
+        .await
+    }
+
+    async fn create_associated_token_account(
+        &mut self,
+        authority: &Pubkey,
+        mint: &Pubkey,
+        payer: &Keypair,
+        token_program_id: &Pubkey,
+    ) -> Result<Pubkey, TestFrameWorkError> {
+        with_client_policy(
+            &self.policy,
+            self.client
+                .create_associated_token_account(authority, mint, payer, token_program_id),
+        )
+        .await
+    }
+
+    async fn write_program_data(
+        &mut self,
+        account: &Pubkey,
+        data_offset: usize,
+        program_data: &[u8],
+        write_ix: &(dyn Fn(u32, Vec<u8>) -> Instruction + Sync),
+        payer: &Keypair,
+        write_authority: &Keypair,
+    ) -> Result<(), TestFrameWorkError> {
+        with_client_policy(
+            &self.policy,
+            self.client.write_program_data(
+                account,
+                data_offset,
+                program_data,
+                write_ix,
+                payer,
+                write_authority,
+            ),
+        )
+        .await
+    }
+
+    async fn get_program_info(
+        &mut self,
+        program_id: &Pubkey,
+    ) -> Result<UpgradeableProgramInfo, TestFrameWorkError> {
+        with_client_policy(&self.policy, self.client.get_program_info(program_id)).await
+    }
+
+    #[cfg(feature = "loader-v4")]
+    async fn get_program_v4_state(
+        &mut self,
+        program_id: &Pubkey,
+    ) -> Result<LoaderV4State, TestFrameWorkError> {
+        with_client_policy(&self.policy, self.client.get_program_v4_state(program_id)).await
+    }
+
+    async fn send_transactions(
+        &mut self,
+        transactions: Vec<Transaction>,
+        mode: SendMode,
+    ) -> Result<BatchOutcome, TestFrameWorkError> {
+        with_client_policy(
+            &self.policy,
+            self.client.send_transactions(transactions, mode),
+        )
+        .await
+    }
+
+    async fn process_instructions(
+        &mut self,
+        ixs: &[Instruction],
+        payer: &Keypair,
+        signers: &[&Keypair],
+    ) -> Result<(), TestFrameWorkError> {
+        with_client_policy(
+            &self.policy,
+            self.client.process_instructions(ixs, payer, signers),
+        )
+        .await
+    }
+}
+
+/// The calls of an `RpcClient` backend the policy sends and confirms transactions with,
+/// and the `ClientExtensions` shared by the `RpcClient` backends are built on.
+#[async_trait]
+pub(crate) trait PolicyClient: Sync {
+    /// Whether the calls block the thread until they return, so the client can only send one request at a time.
+    const BLOCKING: bool;
+
+    fn client_commitment(&self) -> CommitmentConfig;
+
+    async fn account(
+        &self,
+        address: &Pubkey,
+        commitment: CommitmentConfig,
+    ) -> Result<Option<Account>, TestFrameWorkError>;
+
+    async fn program_accounts(
+        &self,
+        program_id: &Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> Result<Vec<(Pubkey, Account)>, TestFrameWorkError>;
+
+    async fn simulate_transaction(
+        &self,
+        transaction: &Transaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> Result<RpcSimulateTransactionResult, TestFrameWorkError>;
+
+    async fn latest_blockhash(
+        &self,
+        commitment: CommitmentConfig,
+    ) -> Result<Hash, TestFrameWorkError>;
+
+    async fn send_transaction(
+        &self,
+        transaction: &VersionedTransaction,
+        config: RpcSendTransactionConfig,
+    ) -> Result<Signature, TestFrameWorkError>;
+
+    async fn is_blockhash_valid(
+        &self,
+        blockhash: &Hash,
+        commitment: CommitmentConfig,
+    ) -> Result<bool, TestFrameWorkError>;
+
+    async fn signature_status(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentConfig,
+    ) -> Result<Option<Result<(), TransactionError>>, TestFrameWorkError>;
+
+    async fn executed_transaction(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentConfig,
+    ) -> Result<EncodedConfirmedTransactionWithStatusMeta, TestFrameWorkError>;
+
+    async fn sleep(&self, duration: Duration);
+}
+
+#[async_trait]
+impl PolicyClient for RpcClient {
+    const BLOCKING: bool = true;
+
+    fn client_commitment(&self) -> CommitmentConfig {
+        self.commitment()
+    }
+
+    async fn account(
+        &self,
+        address: &Pubkey,
+        commitment: CommitmentConfig,
+    ) -> Result<Option<Account>, TestFrameWorkError> {
+        Ok(self.get_account_with_commitment(address, commitment)?.value)
+    }
+
+    async fn program_accounts(
+        &self,
+        program_id: &Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> Result<Vec<(Pubkey, Account)>, TestFrameWorkError> {
+        Ok(self.get_program_accounts_with_config(program_id, config)?)
+    }
+
+    async fn simulate_transaction(
+        &self,
+        transaction: &Transaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> Result<RpcSimulateTransactionResult, TestFrameWorkError> {
+        Ok(self
+            .simulate_transaction_with_config(transaction, config)?
+            .value)
+    }
+
+    async fn latest_blockhash(
+        &self,
+        commitment: CommitmentConfig,
+    ) -> Result<Hash, TestFrameWorkError> {
+        Ok(self.get_latest_blockhash_with_commitment(commitment)?.0)
+    }
+
+    async fn send_transaction(
+        &self,
+        transaction: &VersionedTransaction,
+        config: RpcSendTransactionConfig,
+    ) -> Result<Signature, TestFrameWorkError> {
+        Ok(self.send_transaction_with_config(transaction, config)?)
+    }
+
+    async fn is_blockhash_valid(
+        &self,
+        blockhash: &Hash,
+        commitment: CommitmentConfig,
+    ) -> Result<bool, TestFrameWorkError> {
+        Ok(RpcClient::is_blockhash_valid(self, blockhash, commitment)?)
+    }
+
+    async fn signature_status(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentConfig,
+    ) -> Result<Option<Result<(), TransactionError>>, TestFrameWorkError> {
+        Ok(self.get_signature_status_with_commitment(signature, commitment)?)
+    }
+
+    async fn executed_transaction(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentConfig,
+    ) -> Result<EncodedConfirmedTransactionWithStatusMeta, TestFrameWorkError> {
+        Ok(self.get_transaction_with_config(signature, rpc_transaction_config(commitment))?)
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+#[async_trait]
+impl PolicyClient for solana_client::nonblocking::rpc_client::RpcClient {
+    const BLOCKING: bool = false;
+
+    fn client_commitment(&self) -> CommitmentConfig {
+        self.commitment()
+    }
+
+    async fn account(
+        &self,
+        address: &Pubkey,
+        commitment: CommitmentConfig,
+    ) -> Result<Option<Account>, TestFrameWorkError> {
+        Ok(self
+            .get_account_with_commitment(address, commitment)
+            .await?
+            .value)
+    }
+
+    async fn program_accounts(
+        &self,
+        program_id: &Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> Result<Vec<(Pubkey, Account)>, TestFrameWorkError> {
+        Ok(self
+            .get_program_accounts_with_config(program_id, config)
+            .await?)
+    }
+
+    async fn simulate_transaction(
+        &self,
+        transaction: &Transaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> Result<RpcSimulateTransactionResult, TestFrameWorkError> {
+        Ok(self
+            .simulate_transaction_with_config(transaction, config)
+            .await?
+            .value)
+    }
+
+    async fn latest_blockhash(
+        &self,
+        commitment: CommitmentConfig,
+    ) -> Result<Hash, TestFrameWorkError> {
+        Ok(self
+            .get_latest_blockhash_with_commitment(commitment)
+            .await?
+            .0)
+    }
+
+    async fn send_transaction(
+        &self,
+        transaction: &VersionedTransaction,
+        config: RpcSendTransactionConfig,
+    ) -> Result<Signature, TestFrameWorkError> {
+        Ok(self
+            .send_transaction_with_config(transaction, config)
+            .await?)
+    }
+
+    async fn is_blockhash_valid(
+        &self,
+        blockhash: &Hash,
+        commitment: CommitmentConfig,
+    ) -> Result<bool, TestFrameWorkError> {
+        Ok(
+            solana_client::nonblocking::rpc_client::RpcClient::is_blockhash_valid(
+                self, blockhash, commitment,
+            )
+            .await?,
+        )
+    }
+
+    async fn signature_status(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentConfig,
+    ) -> Result<Option<Result<(), TransactionError>>, TestFrameWorkError> {
+        Ok(self
+            .get_signature_status_with_commitment(signature, commitment)
+            .await?)
+    }
+
+    async fn executed_transaction(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentConfig,
+    ) -> Result<EncodedConfirmedTransactionWithStatusMeta, TestFrameWorkError> {
+        Ok(self
+            .get_transaction_with_config(signature, rpc_transaction_config(commitment))
+            .await?)
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+}
+
+/// Fetch the latest blockhash at the commitment of the `RpcSendPolicy` of the call.
+pub(crate) async fn latest_blockhash<C: PolicyClient>(
+    client: &C,
+) -> Result<Hash, TestFrameWorkError> {
+    client
+        .latest_blockhash(rpc_commitment(client.client_commitment()))
+        .await
+}
+
+/// Send a transaction and wait for its confirmation according to the `RpcSendPolicy` of the call.
+pub(crate) async fn send_and_confirm<C: PolicyClient>(
+    client: &C,
+    transaction: PolicyTransaction<'_>,
+) -> Result<Signature, TestFrameWorkError> {
+    let (signature, result) = send_with_policy(client, transaction, &rpc_send_policy()).await?;
+
+    result.map(|_| signature).map_err(Into::into)
+}
+
+/// Send a transaction and wait for its confirmation according to the policy.
+/// Returns the signature together with the result of the executed transaction.
+async fn send_with_policy<C: PolicyClient>(
+    client: &C,
+    transaction: PolicyTransaction<'_>,
+    policy: &RpcSendPolicy,
+) -> Result<(Signature, Result<(), TransactionError>), TestFrameWorkError> {
+    let commitment = policy.commitment_or(client.client_commitment());
+    let mut retries = 0;
+
+    loop {
+        let signed_transaction = match &transaction {
+            PolicyTransaction::Signed(transaction) => transaction.clone(),
+            PolicyTransaction::Resignable(sign) => {
+                sign(client.latest_blockhash(commitment).await?)?.into()
+            }
+        };
+
+        let result = match client
+            .send_transaction(
+                &signed_transaction,
+                RpcSendTransactionConfig {
+                    preflight_commitment: Some(commitment.commitment),
+                    ..RpcSendTransactionConfig::default()
+                },
+            )
+            .await
+        {
+            Ok(signature) => {
+                confirm_transaction(
+                    client,
+                    signature,
+                    // durable nonce transactions do not expire with their blockhash
+                    (!signed_transaction.uses_durable_nonce())
+                        .then(|| signed_transaction.message.recent_blockhash()),
+                    policy,
+                    commitment,
+                )
+                .await
+            }
+            Err(error) => Err(error),
+        };
+
+        match result {
+            Err(error)
+                if retries < policy.max_retries && transaction.is_retryable(policy, &error) =>
+            {
+                retries += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Send a transaction of a batch and fetch its logs and compute units once it is executed.
+pub(crate) async fn send_with_outcome<C: PolicyClient>(
+    client: &C,
+    transaction: Transaction,
+    policy: &RpcSendPolicy,
+) -> Result<TransactionOutcome, TestFrameWorkError> {
+    let (signature, result) = send_with_policy(
+        client,
+        PolicyTransaction::Signed(transaction.into()),
+        policy,
+    )
+    .await?;
+    let transaction = client
+        .executed_transaction(&signature, policy.commitment_or(client.client_commitment()))
+        .await?;
+
+    transaction_outcome_from_rpc(signature, result, transaction)
+}
+
+/// Poll the status of a sent transaction until it is confirmed, its blockhash expires or the policy times out.
+/// Without a recent blockhash the transaction only times out.
+/// Returns the signature together with the result of the executed transaction.
+async fn confirm_transaction<C: PolicyClient>(
+    client: &C,
+    signature: Signature,
+    recent_blockhash: Option<&Hash>,
+    policy: &RpcSendPolicy,
+    commitment: CommitmentConfig,
+) -> Result<(Signature, Result<(), TransactionError>), TestFrameWorkError> {
+    let started = Instant::now();
+
+    loop {
+        let blockhash_valid = match recent_blockhash {
+            Some(recent_blockhash) => {
+                client
+                    .is_blockhash_valid(recent_blockhash, commitment)
+                    .await?
+            }
+            None => true,
+        };
+        // the status is checked after the blockhash, the transaction can land right before it expires
+        if let Some(result) = client.signature_status(&signature, commitment).await? {
+            return Ok((signature, result));
+        }
+        if !blockhash_valid {
+            return Err(TestFrameWorkError::BlockhashExpired(signature));
+        }
+        if started.elapsed() >= policy.confirmation_timeout {
+            return Err(TestFrameWorkError::ConfirmationTimeout(signature));
+        }
+
+        client.sleep(policy.poll_interval).await;
+    }
+}
@@ -0,0 +1,97 @@
+use crate::error::TestFrameWorkError;
+use solana_sdk::{
+    account::Account,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use spl_token_2022::extension::{
+    BaseState, BaseStateWithExtensions, ExtensionType, StateWithExtensionsOwned,
+};
+
+/// Authority of an SPL token operation, either a single signer or an SPL Token multisig
+/// together with the members signing the operation.
+#[derive(Debug)]
+pub struct TokenAuthority<'a> {
+    pub(crate) address: Pubkey,
+    pub(crate) signers: Vec<&'a Keypair>,
+    multisig_signers: Vec<Pubkey>,
+}
+
+impl<'a> TokenAuthority<'a> {
+    pub fn single(authority: &'a Keypair) -> Self {
+        Self {
+            address: authority.pubkey(),
+            signers: vec![authority],
+            multisig_signers: Vec::new(),
+        }
+    }
+
+    pub fn multisig(multisig: &Pubkey, signers: &[&'a Keypair]) -> Self {
+        Self {
+            address: *multisig,
+            signers: signers.to_vec(),
+            multisig_signers: signers.iter().map(|signer| signer.pubkey()).collect(),
+        }
+    }
+
+    /// The multisig members signing the operation, empty for a single authority.
+    pub(crate) fn multisig_signers(&self) -> Vec<&Pubkey> {
+        self.multisig_signers.iter().collect()
+    }
+}
+
+impl<'a> From<&'a Keypair> for TokenAuthority<'a> {
+    fn from(authority: &'a Keypair) -> Self {
+        Self::single(authority)
+    }
+}
+
+/// A mint or token account owned by either SPL Token or Token-2022, read with
+/// `ClientExtensions::get_mint` or `get_token_account`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenState<S: BaseState> {
+    /// The token program owning the account
+    pub token_program_id: Pubkey,
+    /// The types of the Token-2022 extensions of the account, empty for SPL Token accounts
+    pub extension_types: Vec<ExtensionType>,
+    /// The base state and the extension data, read extensions with `BaseStateWithExtensions::get_extension`
+    pub state: StateWithExtensionsOwned<S>,
+}
+
+impl<S: BaseState> TokenState<S> {
+    /// The mint or token account state shared by both token programs.
+    pub fn base(&self) -> &S {
+        &self.state.base
+    }
+}
+
+/// The native mint of SPL Token or Token-2022.
+pub(crate) fn native_mint(token_program_id: &Pubkey) -> Pubkey {
+    if *token_program_id == spl_token_2022::id() {
+        spl_token_2022::native_mint::id()
+    } else {
+        spl_token::native_mint::id()
+    }
+}
+
+/// Unpack a mint or token account of either token program.
+pub(crate) fn unpack_token_state<S: BaseState>(
+    address: Pubkey,
+    account: Account,
+) -> Result<TokenState<S>, TestFrameWorkError> {
+    if account.owner != spl_token::id() && account.owner != spl_token_2022::id() {
+        return Err(TestFrameWorkError::deserialize::<S>(address));
+    }
+
+    let state = StateWithExtensionsOwned::<S>::unpack(account.data)
+        .map_err(|_| TestFrameWorkError::deserialize::<S>(address))?;
+    let extension_types = state
+        .get_extension_types()
+        .map_err(|_| TestFrameWorkError::deserialize::<S>(address))?;
+
+    Ok(TokenState {
+        token_program_id: account.owner,
+        extension_types,
+        state,
+    })
+}
@@ -0,0 +1,156 @@
+use super::{return_data_from_rpc, rpc_send_policy, RpcSendPolicy, TransactionOutcome};
+use crate::error::TestFrameWorkError;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    signature::Signature,
+    transaction::{Transaction, TransactionError},
+};
+use solana_transaction_status::{EncodedConfirmedTransactionWithStatusMeta, UiTransactionEncoding};
+use std::future::Future;
+
+/// How `ClientExtensions::send_transactions` sends a batch of transactions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SendMode {
+    /// Send each transaction after the previous one is executed
+    Sequential,
+    /// Send all transactions at once
+    ConcurrentUnordered,
+    /// Send transactions at once, except that a transaction is only sent after the transactions whose
+    /// indices are listed for it were executed, and is skipped if one of them failed.
+    /// Transactions can only depend on transactions before them in the batch.
+    ConcurrentWithDependencies(Vec<Vec<usize>>),
+}
+
+/// Per-transaction results of a batch in batch order, an error for transactions that were not executed
+pub type BatchOutcome = Vec<Result<TransactionOutcome, TestFrameWorkError>>;
+
+/// Group the transactions of a batch into waves, every transaction of a wave only depends on earlier waves.
+fn transaction_waves(len: usize, mode: &SendMode) -> Result<Vec<Vec<usize>>, TestFrameWorkError> {
+    let depths: Vec<usize> = match mode {
+        SendMode::Sequential => (0..len).collect(),
+        SendMode::ConcurrentUnordered => vec![0; len],
+        SendMode::ConcurrentWithDependencies(dependencies) => {
+            if dependencies.len() > len {
+                return Err(TestFrameWorkError::Error(
+                    "More dependency lists than transactions in the batch",
+                ));
+            }
+
+            let mut depths = vec![0; len];
+            for (i, dependencies) in dependencies.iter().enumerate() {
+                for &j in dependencies {
+                    if j >= i {
+                        return Err(TestFrameWorkError::Error(
+                            "Transactions can only depend on transactions before them in the batch",
+                        ));
+                    }
+                    depths[i] = depths[i].max(depths[j] + 1);
+                }
+            }
+            depths
+        }
+    };
+
+    let mut waves = vec![Vec::new(); depths.iter().max().map_or(0, |depth| depth + 1)];
+    for (i, depth) in depths.into_iter().enumerate() {
+        waves[depth].push(i);
+    }
+
+    Ok(waves)
+}
+
+/// Send a batch wave by wave with `send_wave`, which returns the results of a wave in order.
+/// A transaction that fails to be sent only fails its own slot and skips the transactions depending on it.
+pub(crate) async fn send_in_waves<F, Fut>(
+    transactions: Vec<Transaction>,
+    mode: &SendMode,
+    mut send_wave: F,
+) -> Result<BatchOutcome, TestFrameWorkError>
+where
+    F: FnMut(Vec<Transaction>) -> Fut,
+    Fut: Future<Output = BatchOutcome>,
+{
+    let waves = transaction_waves(transactions.len(), mode)?;
+    let mut transactions: Vec<Option<Transaction>> = transactions.into_iter().map(Some).collect();
+    let mut results: Vec<Option<Result<TransactionOutcome, TestFrameWorkError>>> =
+        transactions.iter().map(|_| None).collect();
+
+    for wave in waves {
+        let mut sent = Vec::with_capacity(wave.len());
+        for i in wave {
+            let dependency_failed = match mode {
+                SendMode::ConcurrentWithDependencies(dependencies) => {
+                    dependencies.get(i).into_iter().flatten().any(
+                        |&j| !matches!(&results[j], Some(Ok(outcome)) if outcome.result.is_ok()),
+                    )
+                }
+                _ => false,
+            };
+
+            if dependency_failed {
+                results[i] = Some(Err(TestFrameWorkError::Error(
+                    "Skipped because a transaction it depends on failed",
+                )));
+            } else {
+                sent.push(i);
+            }
+        }
+
+        let wave_transactions = sent
+            .iter()
+            .map(|&i| transactions[i].take().unwrap())
+            .collect();
+        for (i, result) in sent.into_iter().zip(send_wave(wave_transactions).await) {
+            results[i] = Some(result);
+        }
+    }
+
+    // every transaction is in exactly one wave
+    Ok(results.into_iter().map(Option::unwrap).collect())
+}
+
+/// The policy the `RpcClient` backends send a batch with. Executed transactions can only be fetched
+/// once they are confirmed, so a batch is confirmed at least at `confirmed` commitment.
+pub(crate) fn batch_send_policy(client_commitment: CommitmentConfig) -> RpcSendPolicy {
+    let policy = rpc_send_policy();
+    if policy
+        .commitment_or(client_commitment)
+        .is_at_least_confirmed()
+    {
+        policy
+    } else {
+        policy.commitment(CommitmentConfig::confirmed())
+    }
+}
+
+/// RPC config for fetching an executed transaction with its logs and compute units.
+pub(crate) fn rpc_transaction_config(commitment: CommitmentConfig) -> RpcTransactionConfig {
+    RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(commitment),
+        max_supported_transaction_version: Some(0),
+    }
+}
+
+/// Map an executed transaction fetched from an RPC node to its outcome.
+pub(crate) fn transaction_outcome_from_rpc(
+    signature: Signature,
+    result: Result<(), TransactionError>,
+    transaction: EncodedConfirmedTransactionWithStatusMeta,
+) -> Result<TransactionOutcome, TestFrameWorkError> {
+    let meta = transaction
+        .transaction
+        .meta
+        .ok_or(TestFrameWorkError::Error(
+            "Transaction status meta not available",
+        ))?;
+
+    Ok(TransactionOutcome {
+        signature,
+        result,
+        logs: Option::from(meta.log_messages).unwrap_or_default(),
+        compute_units_consumed: Option::from(meta.compute_units_consumed).unwrap_or_default(),
+        return_data: return_data_from_rpc(meta.return_data.into())?,
+    })
+}
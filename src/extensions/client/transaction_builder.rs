@@ -0,0 +1,178 @@
+use crate::error::TestFrameWorkError;
+use solana_sdk::{
+    address_lookup_table::{state::AddressLookupTable, AddressLookupTableAccount},
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::Instruction,
+    message::{v0, Message, VersionedMessage},
+    nonce::state::{State as NonceState, Versions as NonceVersions},
+    pubkey::Pubkey,
+    system_instruction,
+};
+
+/// Where the recent blockhash of a built transaction comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecentBlockhash {
+    /// Fetch the latest blockhash from the client
+    Latest,
+    /// Use the given blockhash
+    Hash(Hash),
+    /// Use the blockhash stored in a durable nonce account and advance the nonce as the first instruction
+    Nonce { account: Pubkey, authority: Pubkey },
+}
+
+/// Description of a transaction, turned into a message or a transaction with
+/// `ClientExtensions::build_message`, `build_transaction` or `build_partially_signed_transaction`.
+#[derive(Debug, Clone)]
+pub struct TransactionBuilder {
+    pub(crate) instructions: Vec<Instruction>,
+    pub(crate) payer: Pubkey,
+    pub(crate) compute_unit_limit: Option<u32>,
+    pub(crate) compute_unit_price: Option<u64>,
+    pub(crate) heap_frame_bytes: Option<u32>,
+    pub(crate) memos: Vec<String>,
+    pub(crate) recent_blockhash: RecentBlockhash,
+    pub(crate) address_lookup_tables: Vec<Pubkey>,
+}
+
+impl TransactionBuilder {
+    pub fn new(ixs: &[Instruction], payer: &Pubkey) -> Self {
+        Self {
+            instructions: ixs.to_vec(),
+            payer: *payer,
+            compute_unit_limit: None,
+            compute_unit_price: None,
+            heap_frame_bytes: None,
+            memos: Vec::new(),
+            recent_blockhash: RecentBlockhash::Latest,
+            address_lookup_tables: Vec::new(),
+        }
+    }
+
+    /// Prepend a `SetComputeUnitLimit` instruction.
+    pub fn compute_unit_limit(mut self, units: u32) -> Self {
+        self.compute_unit_limit = Some(units);
+        self
+    }
+
+    /// Prepend a `SetComputeUnitPrice` instruction, the priority fee in micro-lamports per compute unit.
+    pub fn compute_unit_price(mut self, micro_lamports: u64) -> Self {
+        self.compute_unit_price = Some(micro_lamports);
+        self
+    }
+
+    /// Prepend a `RequestHeapFrame` instruction.
+    pub fn heap_frame(mut self, bytes: u32) -> Self {
+        self.heap_frame_bytes = Some(bytes);
+        self
+    }
+
+    /// Append an SPL memo instruction.
+    pub fn memo(mut self, memo: &str) -> Self {
+        self.memos.push(memo.to_string());
+        self
+    }
+
+    /// Use the given recent blockhash instead of fetching the latest one.
+    pub fn recent_blockhash(mut self, blockhash: Hash) -> Self {
+        self.recent_blockhash = RecentBlockhash::Hash(blockhash);
+        self
+    }
+
+    /// Use the durable nonce stored in `nonce_account`, advanced by `nonce_authority`.
+    pub fn nonce(mut self, nonce_account: &Pubkey, nonce_authority: &Pubkey) -> Self {
+        self.recent_blockhash = RecentBlockhash::Nonce {
+            account: *nonce_account,
+            authority: *nonce_authority,
+        };
+        self
+    }
+
+    /// Resolve accounts through the address lookup table at `address` when building a versioned transaction.
+    pub fn address_lookup_table(mut self, address: &Pubkey) -> Self {
+        self.address_lookup_tables.push(*address);
+        self
+    }
+
+    /// All instructions of the transaction, in order: nonce advance, compute budget, instructions, memos.
+    pub fn all_instructions(&self) -> Vec<Instruction> {
+        let mut ixs = Vec::new();
+
+        if let RecentBlockhash::Nonce { account, authority } = self.recent_blockhash {
+            ixs.push(system_instruction::advance_nonce_account(
+                &account, &authority,
+            ));
+        }
+        if let Some(units) = self.compute_unit_limit {
+            ixs.push(ComputeBudgetInstruction::set_compute_unit_limit(units));
+        }
+        if let Some(micro_lamports) = self.compute_unit_price {
+            ixs.push(ComputeBudgetInstruction::set_compute_unit_price(
+                micro_lamports,
+            ));
+        }
+        if let Some(bytes) = self.heap_frame_bytes {
+            ixs.push(ComputeBudgetInstruction::request_heap_frame(bytes));
+        }
+        ixs.extend(self.instructions.iter().cloned());
+        ixs.extend(
+            self.memos
+                .iter()
+                .map(|memo| spl_memo::build_memo(memo.as_bytes(), &[])),
+        );
+
+        ixs
+    }
+
+    /// Compile the unsigned message with the given recent blockhash.
+    pub fn message(&self, recent_blockhash: Hash) -> Message {
+        Message::new_with_blockhash(
+            &self.all_instructions(),
+            Some(&self.payer),
+            &recent_blockhash,
+        )
+    }
+
+    /// Compile the unsigned v0 message with the given recent blockhash and lookup tables.
+    pub fn versioned_message(
+        &self,
+        recent_blockhash: Hash,
+        address_lookup_tables: &[AddressLookupTableAccount],
+    ) -> Result<VersionedMessage, TestFrameWorkError> {
+        let message = v0::Message::try_compile(
+            &self.payer,
+            &self.all_instructions(),
+            address_lookup_tables,
+            recent_blockhash,
+        )?;
+
+        Ok(VersionedMessage::V0(message))
+    }
+}
+
+/// Read the durable nonce stored in a nonce account.
+pub(crate) fn nonce_blockhash(address: Pubkey, data: &[u8]) -> Result<Hash, TestFrameWorkError> {
+    match bincode::deserialize::<NonceVersions>(data) {
+        Ok(versions) => match versions.state() {
+            NonceState::Initialized(nonce_data) => Ok(nonce_data.blockhash()),
+            NonceState::Uninitialized => {
+                Err(TestFrameWorkError::deserialize::<NonceVersions>(address))
+            }
+        },
+        Err(_) => Err(TestFrameWorkError::deserialize::<NonceVersions>(address)),
+    }
+}
+
+/// Read the addresses stored in an address lookup table account.
+pub(crate) fn lookup_table_account(
+    address: Pubkey,
+    data: &[u8],
+) -> Result<AddressLookupTableAccount, TestFrameWorkError> {
+    let table = AddressLookupTable::deserialize(data)
+        .map_err(|_| TestFrameWorkError::deserialize::<AddressLookupTable>(address))?;
+
+    Ok(AddressLookupTableAccount {
+        key: address,
+        addresses: table.addresses.to_vec(),
+    })
+}
@@ -0,0 +1,64 @@
+use crate::error::TestFrameWorkError;
+use solana_program::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+use solana_sdk::{account::Account, clock::Slot, pubkey::Pubkey};
+
+/// State of a program deployed with the upgradeable BPF loader, read with `ClientExtensions::get_program_info`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpgradeableProgramInfo {
+    /// Address of the account holding the program data
+    pub programdata_address: Pubkey,
+    /// Authority allowed to upgrade the program, `None` if the program is immutable
+    pub upgrade_authority: Option<Pubkey>,
+    /// Slot the program was last deployed or upgraded in
+    pub last_deploy_slot: Slot,
+    /// Maximum length of the program, the program data account length without its metadata
+    pub data_len: usize,
+}
+
+/// Read the program data address stored in an upgradeable program account.
+pub(crate) fn programdata_address(
+    program_id: Pubkey,
+    program: &Account,
+) -> Result<Pubkey, TestFrameWorkError> {
+    if program.owner != bpf_loader_upgradeable::id() {
+        return Err(TestFrameWorkError::deserialize::<UpgradeableLoaderState>(
+            program_id,
+        ));
+    }
+
+    match bincode::deserialize(&program.data) {
+        Ok(UpgradeableLoaderState::Program {
+            programdata_address,
+        }) => Ok(programdata_address),
+        _ => Err(TestFrameWorkError::deserialize::<UpgradeableLoaderState>(
+            program_id,
+        )),
+    }
+}
+
+/// Read the state of an upgradeable program from its program data account.
+pub(crate) fn upgradeable_program_info(
+    programdata_address: Pubkey,
+    programdata: &Account,
+) -> Result<UpgradeableProgramInfo, TestFrameWorkError> {
+    let metadata_len = UpgradeableLoaderState::size_of_programdata_metadata();
+
+    match programdata
+        .data
+        .get(..metadata_len)
+        .map(bincode::deserialize)
+    {
+        Some(Ok(UpgradeableLoaderState::ProgramData {
+            slot,
+            upgrade_authority_address,
+        })) => Ok(UpgradeableProgramInfo {
+            programdata_address,
+            upgrade_authority: upgrade_authority_address,
+            last_deploy_slot: slot,
+            data_len: programdata.data.len() - metadata_len,
+        }),
+        _ => Err(TestFrameWorkError::deserialize::<UpgradeableLoaderState>(
+            programdata_address,
+        )),
+    }
+}
@@ -1,7 +1,11 @@
+pub(crate) mod account_tracker;
 mod client;
 mod program_test_context;
 mod program_test;
+pub(crate) mod test_state;
+pub(crate) mod transaction_hooks;
 
 pub use client::*;
 pub use program_test_context::*;
-pub use program_test::*;
\ No newline at end of file
+pub use program_test::*;
+pub use transaction_hooks::*;
\ No newline at end of file
@@ -1,3 +1,4 @@
+use super::{test_state::TestState, TrackedBanksClient, TrackedContext};
 use borsh::BorshSerialize;
 use chrono_humanize::{Accuracy, HumanTime, Tense};
 use log::info;
@@ -10,16 +11,31 @@ use solana_program_runtime::invoke_context::BuiltinFunctionWithContext;
 use solana_program_test::ProgramTest;
 use solana_sdk::{
     account::Account,
+    address_lookup_table::{
+        self,
+        state::{LookupTableMeta, ProgramState, LOOKUP_TABLE_META_SIZE},
+    },
+    hash::Hash,
     native_token::sol_to_lamports,
     pubkey::Pubkey,
     signature::{Keypair, Signer},
     sysvar::rent::Rent,
 };
 use spl_associated_token_account::get_associated_token_address;
+use std::ops::{Deref, DerefMut};
 
 #[cfg(feature = "anchor")]
 use anchor_lang::{AnchorSerialize, Discriminator};
 
+#[cfg(feature = "idl")]
+use {crate::idl::AnchorIdl, serde_json::Value};
+
+#[cfg(feature = "loader-v4")]
+use {
+    super::{loader_v4_state_bytes, LoaderV4State, LoaderV4Status},
+    solana_sdk::loader_v4,
+};
+
 #[cfg(feature = "pyth")]
 use {
     crate::util::PriceAccountWrapper,
@@ -27,6 +43,134 @@ use {
     solana_program_test::BanksClientError,
 };
 
+/// A `ProgramTest` that records the addresses of the accounts added to it, so `get_program_accounts`
+/// on its `TrackedBanksClient` finds them. Accounts added with the `ProgramTest` methods it dereferences to,
+/// such as `add_program`, are not recorded.
+#[derive(Default)]
+pub struct TrackedProgramTest {
+    program_test: ProgramTest,
+    state: TestState,
+}
+
+impl TrackedProgramTest {
+    pub fn new(program_test: ProgramTest) -> Self {
+        Self {
+            program_test,
+            state: TestState::default(),
+        }
+    }
+
+    /// Add an account to the test environment, see `ProgramTest::add_account`.
+    pub fn add_account(&mut self, address: Pubkey, account: Account) {
+        add_tracked_account(self, address, account);
+    }
+
+    /// Add an account with data read from a file, see `ProgramTest::add_account_with_file_data`.
+    pub fn add_account_with_file_data(
+        &mut self,
+        address: Pubkey,
+        lamports: u64,
+        owner: Pubkey,
+        filename: &str,
+    ) {
+        self.state.accounts.track(address);
+        self.program_test
+            .add_account_with_file_data(address, lamports, owner, filename);
+    }
+
+    /// Add an account with base64 encoded data, see `ProgramTest::add_account_with_base64_data`.
+    pub fn add_account_with_base64_data(
+        &mut self,
+        address: Pubkey,
+        lamports: u64,
+        owner: Pubkey,
+        data_base64: &str,
+    ) {
+        self.state.accounts.track(address);
+        self.program_test
+            .add_account_with_base64_data(address, lamports, owner, data_base64);
+    }
+
+    /// Start the test environment, see `ProgramTest::start`.
+    pub async fn start(self) -> (TrackedBanksClient, Keypair, Hash) {
+        let (banks_client, payer, recent_blockhash) = self.program_test.start().await;
+        self.state.accounts.track(payer.pubkey());
+
+        (
+            TrackedBanksClient::new(banks_client, self.state),
+            payer,
+            recent_blockhash,
+        )
+    }
+
+    /// Start the test environment with a context, see `ProgramTest::start_with_context`.
+    pub async fn start_with_context(self) -> TrackedContext {
+        let context = self.program_test.start_with_context().await;
+        self.state.accounts.track(context.payer.pubkey());
+
+        TrackedContext::new(context, self.state)
+    }
+}
+
+impl From<ProgramTest> for TrackedProgramTest {
+    fn from(program_test: ProgramTest) -> Self {
+        Self::new(program_test)
+    }
+}
+
+impl Deref for TrackedProgramTest {
+    type Target = ProgramTest;
+
+    fn deref(&self) -> &ProgramTest {
+        &self.program_test
+    }
+}
+
+impl DerefMut for TrackedProgramTest {
+    fn deref_mut(&mut self) -> &mut ProgramTest {
+        &mut self.program_test
+    }
+}
+
+/// A `ProgramTest` the `ProgramTestExtension` methods run on, with the state of its test if it is tracked.
+pub(crate) trait AsProgramTest {
+    fn program_test(&mut self) -> &mut ProgramTest;
+
+    fn test_state(&self) -> Option<&TestState>;
+}
+
+impl AsProgramTest for ProgramTest {
+    fn program_test(&mut self) -> &mut ProgramTest {
+        self
+    }
+
+    fn test_state(&self) -> Option<&TestState> {
+        None
+    }
+}
+
+impl AsProgramTest for TrackedProgramTest {
+    fn program_test(&mut self) -> &mut ProgramTest {
+        &mut self.program_test
+    }
+
+    fn test_state(&self) -> Option<&TestState> {
+        Some(&self.state)
+    }
+}
+
+/// Add an account, recording its address if the test is tracked.
+fn add_tracked_account<T: AsProgramTest + ?Sized>(
+    program_test: &mut T,
+    address: Pubkey,
+    account: Account,
+) {
+    if let Some(state) = program_test.test_state() {
+        state.accounts.track(address);
+    }
+    program_test.program_test().add_account(address, account);
+}
+
 pub trait ProgramTestExtension {
     /// Adds a requested number of account with initial balance of 1_000 SOL to the test environment
     fn generate_accounts(&mut self, number_of_accounts: u8) -> Vec<Keypair>;
@@ -59,6 +203,17 @@ pub trait ProgramTestExtension {
         size: usize,
     );
 
+    #[cfg(feature = "idl")]
+    /// Adds an account of a type defined in an Anchor IDL, encoded from JSON.
+    /// The account is owned by the program of the IDL.
+    fn add_account_with_json(
+        &mut self,
+        pubkey: Pubkey,
+        idl: &AnchorIdl,
+        account_type: &str,
+        value: Value,
+    );
+
     /// Adds an account with the given balance to the test environment.
     fn add_account_with_lamports(&mut self, pubkey: Pubkey, owner: Pubkey, lamports: u64);
 
@@ -79,6 +234,7 @@ pub trait ProgramTestExtension {
     );
 
     /// Adds an SPL Token account to the test environment.
+    /// A native account (`is_native` holding its rent-exempt reserve) is funded with the reserve plus `amount`.
     fn add_token_account(
         &mut self,
         pubkey: Pubkey,
@@ -91,6 +247,10 @@ pub trait ProgramTestExtension {
         close_authority: Option<Pubkey>,
     );
 
+    /// Adds a wrapped SOL token account holding `amount` lamports to the test environment.
+    /// The account is funded with the rent-exempt reserve plus `amount`, as created by the SPL Token program.
+    fn add_native_token_account(&mut self, pubkey: Pubkey, owner: Pubkey, amount: u64);
+
     /// Adds an associated token account to the test environment.
     /// Returns the address of the created account.
     fn add_associated_token_account(
@@ -105,7 +265,8 @@ pub trait ProgramTestExtension {
     ) -> Pubkey;
 
     /// Adds a BPF program to the test environment.
-    /// The program is upgradeable if `Some` `program_authority` is provided.
+    /// The program is upgradeable if `Some` `program_authority` is provided,
+    /// its program data account is then stored at the canonical address derived by the upgradeable loader.
     fn add_bpf_program(
         &mut self,
         program_name: &str,
@@ -117,6 +278,8 @@ pub trait ProgramTestExtension {
      /// Adds a BPF program to the test environment.
     /// The program is upgradeable if `Some` `program_authority` and then providing the  program data account 
     /// This is useful for those programs which the program data has to be a spefic one, if not, use add_bpf_program
+    /// The loader only accepts the program data account at its canonical address, so `upgrade_program`,
+    /// `extend_program` and `close_program` fail for a program added with any other `program_data` address.
     fn add_bpf_program_with_program_data(
         &mut self,
         program_name: &str,
@@ -126,6 +289,27 @@ pub trait ProgramTestExtension {
         process_instruction: Option<BuiltinFunctionWithContext>,
     );
 
+    /// Adds a loader-v4 program in the given status to the test environment,
+    /// `authority` can upgrade and retract it with the `ClientExtensions` loader-v4 methods.
+    /// The debug assertions of solana-runtime must be turned off in the workspace profile,
+    /// otherwise the runtime panics when it loads the program.
+    #[cfg(feature = "loader-v4")]
+    fn add_loader_v4_program(
+        &mut self,
+        program_name: &str,
+        program_id: Pubkey,
+        authority: Pubkey,
+        status: LoaderV4Status,
+    );
+
+    /// Adds an active address lookup table holding the given addresses to the test environment.
+    fn add_address_lookup_table(
+        &mut self,
+        address: Pubkey,
+        authority: Option<Pubkey>,
+        addresses: &[Pubkey],
+    );
+
     #[cfg(feature = "pyth")]
     /// Adds a Pyth oracle to the test environment.
     fn add_pyth_oracle(
@@ -138,7 +322,7 @@ pub trait ProgramTestExtension {
     ) -> Result<(), BanksClientError>;
 }
 
-impl ProgramTestExtension for ProgramTest {
+impl<E: AsProgramTest> ProgramTestExtension for E {
     fn generate_accounts(&mut self, number_of_accounts: u8) -> Vec<Keypair> {
         let mut accounts: Vec<Keypair> = vec![];
 
@@ -158,7 +342,8 @@ impl ProgramTestExtension for ProgramTest {
         data: &[u8],
         executable: bool,
     ) {
-        self.add_account(
+        add_tracked_account(
+            self,
             pubkey,
             Account {
                 lamports: Rent::default().minimum_balance(data.len()),
@@ -204,8 +389,23 @@ impl ProgramTestExtension for ProgramTest {
         self.add_account_with_data(pubkey, owner, &v, false);
     }
 
+    #[cfg(feature = "idl")]
+    fn add_account_with_json(
+        &mut self,
+        pubkey: Pubkey,
+        idl: &AnchorIdl,
+        account_type: &str,
+        value: Value,
+    ) {
+        let data = idl
+            .encode_account(account_type, &value)
+            .unwrap_or_else(|error| panic!("Cannot encode provided account: {}", error));
+        self.add_account_with_data(pubkey, idl.program_id(), &data, false);
+    }
+
     fn add_account_with_lamports(&mut self, pubkey: Pubkey, owner: Pubkey, lamports: u64) {
-        self.add_account(
+        add_tracked_account(
+            self,
             pubkey,
             Account {
                 lamports,
@@ -274,22 +474,53 @@ impl ProgramTestExtension for ProgramTest {
         delegated_amount: u64,
         close_authority: Option<Pubkey>,
     ) {
-        self.add_account_with_packable(
+        let mut data = vec![0u8; spl_token::state::Account::LEN];
+        spl_token::state::Account {
+            mint,
+            owner,
+            amount,
+            delegate: COption::from(delegate),
+            state: spl_token::state::AccountState::Initialized,
+            is_native: COption::from(is_native),
+            delegated_amount,
+            close_authority: COption::from(close_authority),
+        }
+        .pack_into_slice(&mut data);
+
+        // the lamports of a native account are its rent-exempt reserve plus the wrapped amount
+        let lamports = match is_native {
+            Some(rent_exempt_reserve) => rent_exempt_reserve + amount,
+            None => Rent::default().minimum_balance(data.len()),
+        };
+
+        add_tracked_account(
+            self,
             pubkey,
-            spl_token::id(),
-            spl_token::state::Account {
-                mint,
-                owner,
-                amount,
-                delegate: COption::from(delegate),
-                state: spl_token::state::AccountState::Initialized,
-                is_native: COption::from(is_native),
-                delegated_amount,
-                close_authority: COption::from(close_authority),
+            Account {
+                lamports,
+                data,
+                executable: false,
+                owner: spl_token::id(),
+                rent_epoch: 0,
             },
         );
     }
 
+    fn add_native_token_account(&mut self, pubkey: Pubkey, owner: Pubkey, amount: u64) {
+        let rent_exempt_reserve = Rent::default().minimum_balance(spl_token::state::Account::LEN);
+
+        self.add_token_account(
+            pubkey,
+            spl_token::native_mint::id(),
+            owner,
+            amount,
+            None,
+            Some(rent_exempt_reserve),
+            0,
+            None,
+        );
+    }
+
     fn add_associated_token_account(
         &mut self,
         mint: Pubkey,
@@ -327,7 +558,9 @@ impl ProgramTestExtension for ProgramTest {
                 solana_program_test::find_file(&format!("{}.so", program_name)).unwrap();
             let program_bytes = solana_program_test::read_file(program_file.clone());
 
-            let program_data_pubkey = Pubkey::new_unique();
+            // the loader only accepts the program data account at its canonical address
+            let (program_data_pubkey, _) =
+                Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
 
             let mut program = Vec::<u8>::new();
             bincode::serialize_into(
@@ -384,7 +617,8 @@ impl ProgramTestExtension for ProgramTest {
                 false,
             );
         } else {
-            self.add_program(program_name, program_id, process_instruction);
+            self.program_test()
+                .add_program(program_name, program_id, process_instruction);
         }
     }
 
@@ -456,10 +690,55 @@ impl ProgramTestExtension for ProgramTest {
                 false,
             );
         } else {
-            self.add_program(program_name, program_id, process_instruction);
+            self.program_test()
+                .add_program(program_name, program_id, process_instruction);
         }
     }
 
+    #[cfg(feature = "loader-v4")]
+    fn add_loader_v4_program(
+        &mut self,
+        program_name: &str,
+        program_id: Pubkey,
+        authority: Pubkey,
+        status: LoaderV4Status,
+    ) {
+        let program_file = solana_program_test::find_file(&format!("{}.so", program_name)).unwrap();
+        let program_bytes = solana_program_test::read_file(program_file);
+        let state = loader_v4_state_bytes(&LoaderV4State {
+            slot: 0,
+            authority_address: authority,
+            status,
+        });
+
+        // the loader keeps its program accounts non-executable, it rejects changes to executable ones
+        self.add_account_with_data(
+            program_id,
+            loader_v4::id(),
+            &[state.as_slice(), program_bytes.as_slice()].concat(),
+            false,
+        );
+    }
+
+    fn add_address_lookup_table(
+        &mut self,
+        address: Pubkey,
+        authority: Option<Pubkey>,
+        addresses: &[Pubkey],
+    ) {
+        let meta = LookupTableMeta {
+            authority,
+            ..LookupTableMeta::default()
+        };
+        let mut data = bincode::serialize(&ProgramState::LookupTable(meta))
+            .expect("Cannot serialize lookup table meta data");
+        // the addresses start after the fixed size meta data
+        data.resize(LOOKUP_TABLE_META_SIZE, 0);
+        data.extend(addresses.iter().flat_map(|address| address.to_bytes()));
+
+        self.add_account_with_data(address, address_lookup_table::program::id(), &data, false);
+    }
+
     #[cfg(feature = "pyth")]
     /// Adds a Pyth oracle to the test environment.
     fn add_pyth_oracle(
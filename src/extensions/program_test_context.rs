@@ -1,19 +1,213 @@
+use super::{
+    test_state::TestState,
+    transaction_hooks::{process_transaction_with_hooks, TransactionHook},
+    AsBanksClient, TrackedBanksClient,
+};
+use crate::{error::TestFrameWorkError, TransactionOutcome};
 use async_trait::async_trait;
-use solana_program::pubkey::Pubkey;
+use solana_program::{
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
 use solana_program_test::{ProgramTestContext, ProgramTestError};
-use solana_sdk::{account::AccountSharedData, sysvar::clock::Clock};
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    instruction::Instruction,
+    signature::{Keypair, Signer},
+    sysvar::clock::Clock,
+    transaction::{Transaction, TransactionError},
+};
+use std::{
+    ops::{Deref, DerefMut},
+    time::Duration,
+};
+
+#[cfg(feature = "anchor")]
+use anchor_lang::{AccountDeserialize, AccountSerialize};
+
+const NANOSECONDS_IN_SECOND: i64 = 1_000_000_000;
 
 #[cfg(feature = "pyth")]
 use {
-    crate::error::TestFrameWorkError,
     crate::util::PriceAccountWrapper,
-    pyth_sdk_solana::state::{PriceAccount, PriceInfo},
+    pyth_sdk_solana::state::{PriceAccount, PriceInfo, PriceStatus},
 };
 
+/// Scripted price path for a Pyth oracle.
+/// Registered with `TrackedContext::add_price_path` to move the oracle price along with the clock.
+#[cfg(feature = "pyth")]
+pub struct PythPricePath {
+    source: PriceSource,
+}
+
+#[cfg(feature = "pyth")]
+enum PriceSource {
+    Points(Vec<(i64, i64, u64)>),
+    Generator(Box<dyn Fn(i64) -> (i64, u64) + Send + Sync>),
+}
+
+#[cfg(feature = "pyth")]
+impl PythPricePath {
+    /// Create a path from a list of `(timestamp, price, conf)` points.
+    /// A point stays in effect until the timestamp of the next one is reached.
+    pub fn from_points(mut points: Vec<(i64, i64, u64)>) -> Self {
+        points.sort_by_key(|(timestamp, _, _)| *timestamp);
+
+        Self {
+            source: PriceSource::Points(points),
+        }
+    }
+
+    /// Create a path from a function mapping a unix timestamp to `(price, conf)`.
+    pub fn from_fn<F>(generator: F) -> Self
+    where
+        F: Fn(i64) -> (i64, u64) + Send + Sync + 'static,
+    {
+        Self {
+            source: PriceSource::Generator(Box::new(generator)),
+        }
+    }
+
+    /// Return the `(price, conf)` pair in effect at the given timestamp.
+    /// If the timestamp precedes the first scripted point, `None` is returned.
+    pub fn price_at(&self, timestamp: i64) -> Option<(i64, u64)> {
+        match &self.source {
+            PriceSource::Points(points) => points
+                .iter()
+                .take_while(|(point_timestamp, _, _)| *point_timestamp <= timestamp)
+                .last()
+                .map(|(_, price, conf)| (*price, *conf)),
+            PriceSource::Generator(generator) => Some(generator(timestamp)),
+        }
+    }
+}
+
+/// A `ProgramTestContext` of a `TrackedProgramTest`, see `TrackedProgramTest::start_with_context`.
+/// Its `banks_client` is a `TrackedBanksClient` and accounts written with `set_account` are recorded,
+/// so `get_program_accounts` finds them. Transaction hooks registered on it apply to every client of the test.
+/// Its `warp_to_timestamp` and advance methods update the oracles of the registered price paths,
+/// the `ProgramTestContext::warp_to_slot` and `warp_to_epoch` it dereferences to do not.
+pub struct TrackedContext {
+    context: ProgramTestContext,
+    pub banks_client: TrackedBanksClient,
+    #[cfg(feature = "pyth")]
+    price_paths: Vec<(Pubkey, PythPricePath)>,
+}
+
+impl TrackedContext {
+    pub(crate) fn new(context: ProgramTestContext, state: TestState) -> Self {
+        let banks_client = TrackedBanksClient::new(context.banks_client.clone(), state);
+
+        Self {
+            context,
+            banks_client,
+            #[cfg(feature = "pyth")]
+            price_paths: Vec::new(),
+        }
+    }
+
+    /// Create or overwrite an account, see `ProgramTestContext::set_account`.
+    pub fn set_account(&mut self, address: &Pubkey, account: &AccountSharedData) {
+        set_tracked_account(self, address, account);
+    }
+
+    /// Register the price path of a Pyth oracle. Every `warp_to_timestamp` and advance of this context
+    /// writes the price the path gives for the new time into the oracle.
+    #[cfg(feature = "pyth")]
+    pub fn add_price_path(&mut self, oracle: Pubkey, price_path: PythPricePath) {
+        self.price_paths.push((oracle, price_path));
+    }
+
+    /// Register a hook run before and after every transaction processed through the framework by the
+    /// clients of this test, including the setup transactions issued by `ClientExtensions`.
+    pub fn add_transaction_hook<H: TransactionHook + 'static>(&mut self, hook: H) {
+        self.banks_client.add_transaction_hook(hook);
+    }
+
+    /// Remove all transaction hooks of this test.
+    pub fn clear_transaction_hooks(&mut self) {
+        self.banks_client.clear_transaction_hooks();
+    }
+
+    /// Return the underlying `ProgramTestContext`, which does not record accounts or run transaction hooks.
+    pub fn into_inner(self) -> ProgramTestContext {
+        self.context
+    }
+}
+
+impl Deref for TrackedContext {
+    type Target = ProgramTestContext;
+
+    fn deref(&self) -> &ProgramTestContext {
+        &self.context
+    }
+}
+
+impl DerefMut for TrackedContext {
+    fn deref_mut(&mut self) -> &mut ProgramTestContext {
+        &mut self.context
+    }
+}
+
+/// A `ProgramTestContext` the `ProgramTestContextExtension` methods run on,
+/// with the state of its test if it is tracked.
+pub(crate) trait AsProgramTestContext: Send {
+    fn program_test_context(&mut self) -> &mut ProgramTestContext;
+
+    fn test_state(&self) -> Option<TestState>;
+
+    #[cfg(feature = "pyth")]
+    fn price_paths(&self) -> &[(Pubkey, PythPricePath)] {
+        &[]
+    }
+}
+
+impl AsProgramTestContext for ProgramTestContext {
+    fn program_test_context(&mut self) -> &mut ProgramTestContext {
+        self
+    }
+
+    fn test_state(&self) -> Option<TestState> {
+        None
+    }
+}
+
+impl AsProgramTestContext for TrackedContext {
+    fn program_test_context(&mut self) -> &mut ProgramTestContext {
+        &mut self.context
+    }
+
+    fn test_state(&self) -> Option<TestState> {
+        self.banks_client.test_state()
+    }
+
+    #[cfg(feature = "pyth")]
+    fn price_paths(&self) -> &[(Pubkey, PythPricePath)] {
+        &self.price_paths
+    }
+}
+
 #[async_trait]
 pub trait ProgramTestContextExtension {
     /// Calculate slot number from the provided timestamp
-    async fn warp_to_timestamp(&mut self, timestamp: i64) -> Result<(), ProgramTestError>;
+    /// The oracles of the price paths registered on a `TrackedContext` are updated for the new time.
+    async fn warp_to_timestamp(&mut self, timestamp: i64) -> Result<(), TestFrameWorkError>;
+
+    /// Warp forward by the given number of slots and advance the clock by the time they take,
+    /// using the slot duration from the genesis config.
+    /// The oracles of the price paths registered on a `TrackedContext` are updated for the new time.
+    async fn advance_slots(&mut self, slots: u64) -> Result<(), TestFrameWorkError>;
+
+    /// Warp forward by the given number of slots and advance the clock by the time they take,
+    /// assuming every slot lasts `slot_duration`. Useful to simulate congested clusters.
+    /// The oracles of the price paths registered on a `TrackedContext` are updated for the new time.
+    async fn advance_slots_with_slot_duration(
+        &mut self,
+        slots: u64,
+        slot_duration: Duration,
+    ) -> Result<(), TestFrameWorkError>;
 
     #[cfg(feature = "pyth")]
     async fn update_pyth_oracle(
@@ -24,39 +218,110 @@ pub trait ProgramTestContextExtension {
         timestamp: Option<i64>,
         valid_slots: Option<u64>,
     ) -> Result<(), TestFrameWorkError>;
+
+    /// Assemble the given instructions into a transaction payed for by the context payer and process it.
+    /// The blockhash is refreshed if it expired and a memo is appended if an identical transaction was already processed.
+    /// A missing required signer returns `TestFrameWorkError::Signer`.
+    async fn send(
+        &mut self,
+        ixs: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<TransactionOutcome, TestFrameWorkError>;
+
+    /// Set the upgrade authority stored in the ProgramData account of an upgradeable program.
+    async fn take_over_program_upgrade_authority(
+        &mut self,
+        program_id: Pubkey,
+        new_authority: Pubkey,
+    ) -> Result<(), TestFrameWorkError>;
+
+    /// Set the mint authority of an SPL Token Mint.
+    /// The freeze authority is replaced as well if it is set.
+    async fn take_over_mint_authority(
+        &mut self,
+        mint: Pubkey,
+        new_authority: Pubkey,
+    ) -> Result<(), TestFrameWorkError>;
+
+    /// Set the owner of an SPL Token account.
+    /// The delegate and the close authority are replaced as well if they are set.
+    async fn take_over_token_account(
+        &mut self,
+        token_account: Pubkey,
+        new_authority: Pubkey,
+    ) -> Result<(), TestFrameWorkError>;
+
+    /// Overwrite the 32 bytes at the given offset of the account data with the new authority.
+    async fn take_over_authority_at_offset(
+        &mut self,
+        address: Pubkey,
+        offset: usize,
+        new_authority: Pubkey,
+    ) -> Result<(), TestFrameWorkError>;
+
+    #[cfg(feature = "anchor")]
+    /// Deserialize an Anchor account, apply `update` to it and write it back.
+    /// Useful for rewriting authority fields of program state.
+    async fn update_anchor_account<T, F>(
+        &mut self,
+        address: Pubkey,
+        update: F,
+    ) -> Result<(), TestFrameWorkError>
+    where
+        T: AccountDeserialize + AccountSerialize + Send,
+        F: FnOnce(&mut T) + Send;
 }
 
 #[async_trait]
-impl ProgramTestContextExtension for ProgramTestContext {
-    async fn warp_to_timestamp(&mut self, timestamp: i64) -> Result<(), ProgramTestError> {
-        const NANOSECONDS_IN_SECOND: i64 = 1_000_000_000;
-
-        let mut clock: Clock = self.banks_client.get_sysvar().await.unwrap();
+impl<C: AsProgramTestContext> ProgramTestContextExtension for C {
+    async fn warp_to_timestamp(&mut self, timestamp: i64) -> Result<(), TestFrameWorkError> {
+        let context = self.program_test_context();
+        let mut clock: Clock = context.banks_client.get_sysvar().await?;
         let now = clock.unix_timestamp;
         let current_slot = clock.slot;
         clock.unix_timestamp = timestamp;
 
         if now >= timestamp {
             println!("Timestamp incorrect. Cannot set time backwards.");
-            return Err(ProgramTestError::InvalidWarpSlot);
+            return Err(ProgramTestError::InvalidWarpSlot.into());
         }
 
-        let ns_per_slot = self.genesis_config().ns_per_slot();
+        let ns_per_slot = context.genesis_config().ns_per_slot();
         let timestamp_diff_ns = timestamp
             .checked_sub(now) //calculate time diff
-            .expect("Problem with timestamp diff calculation.")
-            .checked_mul(NANOSECONDS_IN_SECOND) //convert from s to ns
-            .expect("Problem with timestamp diff calculation.")
-            as u128;
+            .and_then(|diff| diff.checked_mul(NANOSECONDS_IN_SECOND)) //convert from s to ns
+            .ok_or(TestFrameWorkError::Error(
+                "Problem with timestamp diff calculation.",
+            ))? as u128;
 
         let slots = timestamp_diff_ns
             .checked_div(ns_per_slot)
-            .expect("Problem with slots from timestamp calculation.") as u64;
+            .ok_or(TestFrameWorkError::Error(
+                "Problem with slots from timestamp calculation.",
+            ))? as u64;
 
-        self.set_sysvar(&clock);
-        self.warp_to_slot(current_slot + slots)?;
+        context.set_sysvar(&clock);
+        context.warp_to_slot(current_slot + slots)?;
 
-        Ok(())
+        apply_price_paths(self).await
+    }
+
+    async fn advance_slots(&mut self, slots: u64) -> Result<(), TestFrameWorkError> {
+        let context = self.program_test_context();
+        let ns_per_slot = context.genesis_config().ns_per_slot();
+        advance_clock(context, slots, ns_per_slot).await?;
+
+        apply_price_paths(self).await
+    }
+
+    async fn advance_slots_with_slot_duration(
+        &mut self,
+        slots: u64,
+        slot_duration: Duration,
+    ) -> Result<(), TestFrameWorkError> {
+        advance_clock(self.program_test_context(), slots, slot_duration.as_nanos()).await?;
+
+        apply_price_paths(self).await
     }
 
     #[cfg(feature = "pyth")]
@@ -68,25 +333,22 @@ impl ProgramTestContextExtension for ProgramTestContext {
         timestamp: Option<i64>,
         valid_slot: Option<u64>,
     ) -> Result<(), TestFrameWorkError> {
-        let mut account = self
-            .banks_client
-            .get_account(address)
-            .await
-            .unwrap()
-            .unwrap();
+        let mut account = get_account(self.program_test_context(), address).await?;
 
         let data = if let Some(price_account) = price_account {
-            bincode::serialize(&PriceAccountWrapper(&price_account)).unwrap()
+            bincode::serialize(&PriceAccountWrapper(&price_account))
+                .map_err(|_| TestFrameWorkError::Error("Failed to serialize price account"))?
         } else if let (Some(price_info), Some(timestamp), Some(valid_slot)) =
             (price_info, timestamp, valid_slot)
         {
-            let mut account_data =
-                *pyth_sdk_solana::state::load_price_account(&account.data).unwrap();
+            let mut account_data = *pyth_sdk_solana::state::load_price_account(&account.data)
+                .map_err(|_| TestFrameWorkError::deserialize::<PriceAccount>(address))?;
             account_data.agg = price_info;
             account_data.timestamp = timestamp;
             account_data.valid_slot = valid_slot;
 
-            bincode::serialize(&PriceAccountWrapper(&account_data)).unwrap()
+            bincode::serialize(&PriceAccountWrapper(&account_data))
+                .map_err(|_| TestFrameWorkError::Error("Failed to serialize price account"))?
         } else {
             return Err(TestFrameWorkError::Error(
                 "Either provide the price_account or price_info, time_stamp and prev_slot",
@@ -96,8 +358,314 @@ impl ProgramTestContextExtension for ProgramTestContext {
         account.data = data;
         let account = AccountSharedData::from(account);
 
-        self.set_account(&address, &account);
+        set_tracked_account(self, &address, &account);
+
+        Ok(())
+    }
+
+    async fn send(
+        &mut self,
+        ixs: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<TransactionOutcome, TestFrameWorkError> {
+        // give up on deduplication after this many memo attempts
+        const MAX_MEMO_NONCE: u64 = 64;
+
+        let state = self.test_state();
+        let context = self.program_test_context();
+        let payer = context.payer.insecure_clone();
+        let mut all_signers = vec![&payer];
+        all_signers.extend_from_slice(signers);
+
+        let mut memo_nonce = 0;
+        let mut blockhash_refreshed = false;
+
+        loop {
+            let mut instructions = ixs.to_vec();
+            if memo_nonce > 0 {
+                instructions.push(spl_memo::build_memo(
+                    format!("solana-test-framework nonce {}", memo_nonce).as_bytes(),
+                    &[],
+                ));
+            }
+
+            let mut tx = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
+            tx.try_sign(&all_signers, context.last_blockhash)?;
+
+            let outcome =
+                process_transaction_with_hooks(&mut context.banks_client, state.as_ref(), tx)
+                    .await?;
+
+            match outcome.result {
+                Err(TransactionError::BlockhashNotFound) if !blockhash_refreshed => {
+                    context.get_new_latest_blockhash().await?;
+                    blockhash_refreshed = true;
+                }
+                Err(TransactionError::AlreadyProcessed) if memo_nonce < MAX_MEMO_NONCE => {
+                    memo_nonce += 1;
+                }
+                _ => return Ok(outcome),
+            }
+        }
+    }
+
+    async fn take_over_program_upgrade_authority(
+        &mut self,
+        program_id: Pubkey,
+        new_authority: Pubkey,
+    ) -> Result<(), TestFrameWorkError> {
+        let program = get_account(self.program_test_context(), program_id).await?;
+        if program.owner != bpf_loader_upgradeable::id() {
+            return Err(TestFrameWorkError::Error("Not an upgradeable program"));
+        }
+        let programdata_address = match bincode::deserialize(&program.data) {
+            Ok(UpgradeableLoaderState::Program {
+                programdata_address,
+            }) => programdata_address,
+            _ => return Err(TestFrameWorkError::Error("Not an upgradeable program")),
+        };
+
+        let mut program_data =
+            get_account(self.program_test_context(), programdata_address).await?;
+        let slot = match bincode::deserialize(&program_data.data) {
+            Ok(UpgradeableLoaderState::ProgramData { slot, .. }) => slot,
+            _ => {
+                return Err(TestFrameWorkError::deserialize::<UpgradeableLoaderState>(
+                    programdata_address,
+                ))
+            }
+        };
+
+        let metadata_len = UpgradeableLoaderState::size_of_programdata_metadata();
+        bincode::serialize_into(
+            &mut program_data.data[..metadata_len],
+            &UpgradeableLoaderState::ProgramData {
+                slot,
+                upgrade_authority_address: Some(new_authority),
+            },
+        )
+        .map_err(|_| TestFrameWorkError::Error("Cannot serialize ProgramData account"))?;
+
+        set_tracked_account(
+            self,
+            &programdata_address,
+            &AccountSharedData::from(program_data),
+        );
+
+        Ok(())
+    }
+
+    async fn take_over_mint_authority(
+        &mut self,
+        mint: Pubkey,
+        new_authority: Pubkey,
+    ) -> Result<(), TestFrameWorkError> {
+        let mut account = get_account(self.program_test_context(), mint).await?;
+        let data = account.data.get_mut(..spl_token::state::Mint::LEN).ok_or(
+            TestFrameWorkError::deserialize::<spl_token::state::Mint>(mint),
+        )?;
+
+        // Token-2022 mints share the base layout, extensions follow it
+        let mut mint_state = spl_token::state::Mint::unpack_unchecked(data)
+            .map_err(|_| TestFrameWorkError::deserialize::<spl_token::state::Mint>(mint))?;
+        mint_state.mint_authority = COption::Some(new_authority);
+        if mint_state.freeze_authority.is_some() {
+            mint_state.freeze_authority = COption::Some(new_authority);
+        }
+        mint_state.pack_into_slice(data);
+
+        set_tracked_account(self, &mint, &AccountSharedData::from(account));
+
+        Ok(())
+    }
+
+    async fn take_over_token_account(
+        &mut self,
+        token_account: Pubkey,
+        new_authority: Pubkey,
+    ) -> Result<(), TestFrameWorkError> {
+        let mut account = get_account(self.program_test_context(), token_account).await?;
+        let data = account
+            .data
+            .get_mut(..spl_token::state::Account::LEN)
+            .ok_or(TestFrameWorkError::deserialize::<spl_token::state::Account>(token_account))?;
+
+        // Token-2022 accounts share the base layout, extensions follow it
+        let mut token_account_state =
+            spl_token::state::Account::unpack_unchecked(data).map_err(|_| {
+                TestFrameWorkError::deserialize::<spl_token::state::Account>(token_account)
+            })?;
+        token_account_state.owner = new_authority;
+        if token_account_state.delegate.is_some() {
+            token_account_state.delegate = COption::Some(new_authority);
+        }
+        if token_account_state.close_authority.is_some() {
+            token_account_state.close_authority = COption::Some(new_authority);
+        }
+        token_account_state.pack_into_slice(data);
+
+        set_tracked_account(self, &token_account, &AccountSharedData::from(account));
+
+        Ok(())
+    }
+
+    async fn take_over_authority_at_offset(
+        &mut self,
+        address: Pubkey,
+        offset: usize,
+        new_authority: Pubkey,
+    ) -> Result<(), TestFrameWorkError> {
+        let mut account = get_account(self.program_test_context(), address).await?;
+        account
+            .data
+            .get_mut(offset..offset.saturating_add(32))
+            .ok_or(TestFrameWorkError::Error(
+                "Offset out of account data bounds",
+            ))?
+            .copy_from_slice(new_authority.as_ref());
+
+        set_tracked_account(self, &address, &AccountSharedData::from(account));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "anchor")]
+    async fn update_anchor_account<T, F>(
+        &mut self,
+        address: Pubkey,
+        update: F,
+    ) -> Result<(), TestFrameWorkError>
+    where
+        T: AccountDeserialize + AccountSerialize + Send,
+        F: FnOnce(&mut T) + Send,
+    {
+        let mut account = get_account(self.program_test_context(), address).await?;
+        let mut state = T::try_deserialize(&mut account.data.as_ref())
+            .map_err(|_| TestFrameWorkError::deserialize::<T>(address))?;
+
+        update(&mut state);
+
+        let mut data = Vec::with_capacity(account.data.len());
+        state
+            .try_serialize(&mut data)
+            .map_err(|_| TestFrameWorkError::Error("Failed to serialize account"))?;
+        if data.len() > account.data.len() {
+            return Err(TestFrameWorkError::Error(
+                "Serialized account does not fit in the account data",
+            ));
+        }
+        account.data[..data.len()].copy_from_slice(&data);
+
+        set_tracked_account(self, &address, &AccountSharedData::from(account));
 
         Ok(())
     }
 }
+
+/// Warp forward by `slots` slots and move the clock by the time they take.
+/// Elapsed seconds are counted on the slot grid, so repeated small steps add up to the same time as one large step.
+async fn advance_clock(
+    context: &mut ProgramTestContext,
+    slots: u64,
+    ns_per_slot: u128,
+) -> Result<(), TestFrameWorkError> {
+    let mut clock: Clock = context.banks_client.get_sysvar().await?;
+
+    let current_slot = clock.slot;
+    let target_slot = current_slot
+        .checked_add(slots)
+        .ok_or(TestFrameWorkError::ProgramTest(
+            ProgramTestError::InvalidWarpSlot,
+        ))?;
+
+    let seconds_at_slot = |slot: u64| (slot as u128 * ns_per_slot) / NANOSECONDS_IN_SECOND as u128;
+    let elapsed_seconds = seconds_at_slot(target_slot) - seconds_at_slot(current_slot);
+
+    clock.unix_timestamp = clock
+        .unix_timestamp
+        .checked_add(elapsed_seconds as i64)
+        .ok_or(TestFrameWorkError::Error(
+            "Problem with timestamp calculation",
+        ))?;
+
+    context.set_sysvar(&clock);
+    context.warp_to_slot(target_slot)?;
+
+    Ok(())
+}
+
+/// Create or overwrite an account, recording its address if the test is tracked.
+fn set_tracked_account<C: AsProgramTestContext + ?Sized>(
+    context: &mut C,
+    address: &Pubkey,
+    account: &AccountSharedData,
+) {
+    if let Some(state) = context.test_state() {
+        state.accounts.track(*address);
+    }
+    context.program_test_context().set_account(address, account);
+}
+
+/// Fetch an account from the context, failing if it does not exist.
+async fn get_account(
+    context: &mut ProgramTestContext,
+    address: Pubkey,
+) -> Result<Account, TestFrameWorkError> {
+    context
+        .banks_client
+        .get_account(address)
+        .await?
+        .ok_or(TestFrameWorkError::AccountNotFound(address))
+}
+
+/// Write the price each registered path gives for the current clock into its oracle.
+#[cfg(feature = "pyth")]
+async fn apply_price_paths<C: AsProgramTestContext>(
+    context: &mut C,
+) -> Result<(), TestFrameWorkError> {
+    let clock: Clock = context
+        .program_test_context()
+        .banks_client
+        .get_sysvar()
+        .await?;
+
+    let prices: Vec<(Pubkey, i64, u64)> = context
+        .price_paths()
+        .iter()
+        .filter_map(|(oracle, price_path)| {
+            price_path
+                .price_at(clock.unix_timestamp)
+                .map(|(price, conf)| (*oracle, price, conf))
+        })
+        .collect();
+
+    for (oracle, price, conf) in prices {
+        let price_info = PriceInfo {
+            price,
+            conf,
+            status: PriceStatus::Trading,
+            pub_slot: clock.slot,
+            ..Default::default()
+        };
+
+        context
+            .update_pyth_oracle(
+                oracle,
+                None,
+                Some(price_info),
+                Some(clock.unix_timestamp),
+                Some(clock.slot),
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Without the `pyth` feature there are no price paths to apply.
+#[cfg(not(feature = "pyth"))]
+async fn apply_price_paths<C: AsProgramTestContext>(
+    _context: &mut C,
+) -> Result<(), TestFrameWorkError> {
+    Ok(())
+}
@@ -0,0 +1,8 @@
+use super::{account_tracker::AccountTracker, transaction_hooks::TransactionHooks};
+
+/// State of one test, shared by its `TrackedProgramTest`, `TrackedContext` and `TrackedBanksClient`s.
+#[derive(Clone, Default)]
+pub(crate) struct TestState {
+    pub(crate) accounts: AccountTracker,
+    pub(crate) hooks: TransactionHooks,
+}
@@ -0,0 +1,201 @@
+use super::{client::lookup_table_account, test_state::TestState};
+use crate::TransactionOutcome;
+use async_trait::async_trait;
+use futures::future::join_all;
+use solana_banks_client::{BanksClient, BanksClientError};
+use solana_program_test::tokio;
+use solana_sdk::transaction::VersionedTransaction;
+use std::sync::{Arc, Mutex};
+
+/// Callbacks run before and after every transaction the framework processes through a `TrackedBanksClient`.
+/// Hooks are registered with `TrackedContext::add_transaction_hook` or `TrackedBanksClient::add_transaction_hook`
+/// and apply to every client of the test. Transactions a hook sends through the `BanksClient` it is given do not
+/// run the hooks, a hook must not send transactions through a `TrackedBanksClient` of its own test.
+#[async_trait]
+pub trait TransactionHook: Send {
+    /// Called before the transaction is processed.
+    async fn before_transaction(
+        &mut self,
+        _banks_client: &mut BanksClient,
+        _transaction: &VersionedTransaction,
+    ) {
+    }
+
+    /// Called after the transaction is processed.
+    async fn after_transaction(
+        &mut self,
+        _banks_client: &mut BanksClient,
+        _transaction: &VersionedTransaction,
+        _outcome: &TransactionOutcome,
+    ) {
+    }
+}
+
+/// Closures are called with `None` before the transaction is processed and with its outcome afterwards.
+#[async_trait]
+impl<F> TransactionHook for F
+where
+    F: FnMut(&VersionedTransaction, Option<&TransactionOutcome>) + Send,
+{
+    async fn before_transaction(
+        &mut self,
+        _banks_client: &mut BanksClient,
+        transaction: &VersionedTransaction,
+    ) {
+        self(transaction, None)
+    }
+
+    async fn after_transaction(
+        &mut self,
+        _banks_client: &mut BanksClient,
+        transaction: &VersionedTransaction,
+        outcome: &TransactionOutcome,
+    ) {
+        self(transaction, Some(outcome))
+    }
+}
+
+type SharedHook = Arc<tokio::sync::Mutex<Box<dyn TransactionHook>>>;
+
+/// Transaction hooks of one test.
+/// Every hook is locked only while it runs, so concurrent transactions run the hooks one at a time.
+#[derive(Clone, Default)]
+pub(crate) struct TransactionHooks(Arc<Mutex<Vec<SharedHook>>>);
+
+impl TransactionHooks {
+    pub(crate) fn add(&self, hook: Box<dyn TransactionHook>) {
+        self.0
+            .lock()
+            .unwrap()
+            .push(Arc::new(tokio::sync::Mutex::new(hook)));
+    }
+
+    pub(crate) fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+
+    /// The hooks registered at the time of the call, in registration order.
+    fn registered(&self) -> Vec<SharedHook> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// Process a transaction and run the registered hooks around it.
+/// The accounts it references are recorded in the state of the test, if there is one.
+pub(crate) async fn process_transaction_with_hooks(
+    banks_client: &mut BanksClient,
+    state: Option<&TestState>,
+    transaction: impl Into<VersionedTransaction>,
+) -> Result<TransactionOutcome, BanksClientError> {
+    process_transactions_with_hooks(banks_client, state, vec![transaction.into()])
+        .await
+        .remove(0)
+}
+
+/// Process a batch of independent transactions and run the registered hooks around each of them.
+/// Without hooks the transactions are processed concurrently. With hooks they are processed one at a time,
+/// so the hooks of a transaction only observe the effects of that transaction.
+/// The outcomes are returned in the order of the transactions, a transaction that could not be processed
+/// fails only its own slot.
+pub(crate) async fn process_transactions_with_hooks(
+    banks_client: &mut BanksClient,
+    state: Option<&TestState>,
+    transactions: Vec<VersionedTransaction>,
+) -> Vec<Result<TransactionOutcome, BanksClientError>> {
+    let hooks = state.map_or_else(Vec::new, |state| state.hooks.registered());
+
+    if hooks.is_empty() {
+        return join_all(transactions.into_iter().map(|transaction| {
+            process_with_registered_hooks(banks_client.clone(), state, &hooks, transaction)
+        }))
+        .await;
+    }
+
+    let mut outcomes = Vec::with_capacity(transactions.len());
+    for transaction in transactions {
+        outcomes.push(
+            process_with_registered_hooks(banks_client.clone(), state, &hooks, transaction).await,
+        );
+    }
+
+    outcomes
+}
+
+/// Run the before hooks, process the transaction and run the after hooks with its outcome.
+async fn process_with_registered_hooks(
+    mut banks_client: BanksClient,
+    state: Option<&TestState>,
+    hooks: &[SharedHook],
+    transaction: VersionedTransaction,
+) -> Result<TransactionOutcome, BanksClientError> {
+    for hook in hooks {
+        hook.lock()
+            .await
+            .before_transaction(&mut banks_client, &transaction)
+            .await;
+    }
+    if let Some(state) = state {
+        track_transaction_accounts(&mut banks_client, state, &transaction).await;
+    }
+
+    let processed = banks_client
+        .process_transaction_with_metadata(transaction.clone())
+        .await?;
+    let (logs, compute_units_consumed, return_data) = match processed.metadata {
+        Some(metadata) => (
+            metadata.log_messages,
+            metadata.compute_units_consumed,
+            metadata.return_data,
+        ),
+        None => (Vec::new(), 0, None),
+    };
+    let outcome = TransactionOutcome {
+        signature: transaction.signatures[0],
+        result: processed.result,
+        logs,
+        compute_units_consumed,
+        return_data,
+    };
+
+    for hook in hooks {
+        hook.lock()
+            .await
+            .after_transaction(&mut banks_client, &transaction, &outcome)
+            .await;
+    }
+
+    Ok(outcome)
+}
+
+/// Remember the accounts referenced by a transaction, including those loaded from its address lookup tables.
+/// Lookup tables that cannot be loaded are skipped, the transaction fails without touching their accounts.
+pub(crate) async fn track_transaction_accounts(
+    banks_client: &mut BanksClient,
+    state: &TestState,
+    transaction: &VersionedTransaction,
+) {
+    state
+        .accounts
+        .track_all(transaction.message.static_account_keys());
+
+    for lookup in transaction
+        .message
+        .address_table_lookups()
+        .unwrap_or_default()
+    {
+        let Ok(Some(account)) = banks_client.get_account(lookup.account_key).await else {
+            continue;
+        };
+        let Ok(table) = lookup_table_account(lookup.account_key, &account.data) else {
+            continue;
+        };
+        let indexes = lookup
+            .writable_indexes
+            .iter()
+            .chain(&lookup.readonly_indexes);
+
+        state
+            .accounts
+            .track_all(indexes.filter_map(|index| table.addresses.get(usize::from(*index))));
+    }
+}
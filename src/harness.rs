@@ -0,0 +1,266 @@
+//! Run the same test body against `ProgramTest` + `BanksClient` and against a local
+//! `solana-test-validator` + nonblocking `RpcClient`, see [`backend_test!`](crate::backend_test).
+//! The blocking `RpcClient` is not a backend, its tests stay in `tests/rpc_client.rs`.
+
+use crate::{
+    error::TestFrameWorkError,
+    extensions::{rpc_commitment, AsBanksClient, ClientExtensions, TrackedBanksClient},
+    TrackedProgramTest,
+};
+use async_trait::async_trait;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_program_test::ProgramTest;
+use solana_sdk::{
+    account::Account, bpf_loader, hash::Hash, pubkey::Pubkey, rent::Rent, signature::Keypair,
+    transaction::Transaction,
+};
+use std::path::PathBuf;
+
+#[cfg(feature = "test-validator")]
+use solana_test_validator::{ProgramInfo, TestValidator, TestValidatorGenesis};
+
+/// The runtime a backend test is running against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// `ProgramTest` + `BanksClient`
+    BanksClient,
+    /// Local `solana-test-validator` + nonblocking `RpcClient`
+    TestValidator,
+}
+
+/// Object safe client operations with the same behavior on every backend.
+/// The method names differ from the inherent client methods so calls resolve the same way on every backend.
+#[async_trait]
+pub trait TestClient: Send {
+    /// The runtime this client talks to.
+    fn backend(&self) -> Backend;
+
+    /// Fetch an account, returning `None` if it does not exist.
+    async fn fetch_account(
+        &mut self,
+        address: Pubkey,
+    ) -> Result<Option<Account>, TestFrameWorkError>;
+
+    /// Fetch the lamport balance of an account.
+    async fn fetch_balance(&mut self, address: Pubkey) -> Result<u64, TestFrameWorkError>;
+
+    /// Fetch the latest blockhash.
+    async fn fetch_latest_blockhash(&mut self) -> Result<Hash, TestFrameWorkError>;
+
+    /// Send a signed transaction and wait until it is executed.
+    async fn execute_transaction(
+        &mut self,
+        transaction: Transaction,
+    ) -> Result<(), TestFrameWorkError>;
+}
+
+#[async_trait]
+impl<C: AsBanksClient> TestClient for C {
+    fn backend(&self) -> Backend {
+        Backend::BanksClient
+    }
+
+    async fn fetch_account(
+        &mut self,
+        address: Pubkey,
+    ) -> Result<Option<Account>, TestFrameWorkError> {
+        Ok(self.banks_client().get_account(address).await?)
+    }
+
+    async fn fetch_balance(&mut self, address: Pubkey) -> Result<u64, TestFrameWorkError> {
+        Ok(self.banks_client().get_balance(address).await?)
+    }
+
+    async fn fetch_latest_blockhash(&mut self) -> Result<Hash, TestFrameWorkError> {
+        Ok(self.banks_client().get_latest_blockhash().await?)
+    }
+
+    async fn execute_transaction(
+        &mut self,
+        transaction: Transaction,
+    ) -> Result<(), TestFrameWorkError> {
+        let state = self.test_state();
+        let outcome = crate::extensions::transaction_hooks::process_transaction_with_hooks(
+            self.banks_client(),
+            state.as_ref(),
+            transaction,
+        )
+        .await?;
+
+        outcome
+            .result
+            .map_err(|error| TestFrameWorkError::transaction(error, outcome.logs))
+    }
+}
+
+#[async_trait]
+impl TestClient for RpcClient {
+    fn backend(&self) -> Backend {
+        Backend::TestValidator
+    }
+
+    async fn fetch_account(
+        &mut self,
+        address: Pubkey,
+    ) -> Result<Option<Account>, TestFrameWorkError> {
+        Ok(self
+            .get_account_with_commitment(&address, rpc_commitment(self.commitment()))
+            .await?
+            .value)
+    }
+
+    async fn fetch_balance(&mut self, address: Pubkey) -> Result<u64, TestFrameWorkError> {
+        Ok(self.get_balance(&address).await?)
+    }
+
+    async fn fetch_latest_blockhash(&mut self) -> Result<Hash, TestFrameWorkError> {
+        Ok(self.get_latest_blockhash().await?)
+    }
+
+    async fn execute_transaction(
+        &mut self,
+        transaction: Transaction,
+    ) -> Result<(), TestFrameWorkError> {
+        ClientExtensions::process_versioned_transaction(self, transaction.into()).await
+    }
+}
+
+/// Description of the initial ledger state, shared by every backend of a backend test.
+#[derive(Debug, Clone, Default)]
+pub struct Fixture {
+    programs: Vec<(Pubkey, PathBuf)>,
+    accounts: Vec<(Pubkey, Account)>,
+}
+
+impl Fixture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Deploy the SBF program at `program_path` to `program_id`, owned by the BPF loader.
+    pub fn add_program(mut self, program_id: Pubkey, program_path: impl Into<PathBuf>) -> Self {
+        self.programs.push((program_id, program_path.into()));
+        self
+    }
+
+    /// Add an account to the initial ledger state.
+    pub fn add_account(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.push((address, account));
+        self
+    }
+
+    /// Start a `ProgramTest` with the fixture, returning its `BanksClient` and a funded payer.
+    pub async fn start_banks_client(
+        &self,
+    ) -> Result<(TrackedBanksClient, Keypair), TestFrameWorkError> {
+        let mut program_test = TrackedProgramTest::new(ProgramTest::default());
+        program_test.prefer_bpf(true);
+
+        for (program_id, program_path) in &self.programs {
+            let data = std::fs::read(program_path)?;
+
+            program_test.add_account(
+                *program_id,
+                Account {
+                    lamports: Rent::default().minimum_balance(data.len()).max(1),
+                    data,
+                    owner: bpf_loader::id(),
+                    executable: true,
+                    rent_epoch: 0,
+                },
+            );
+        }
+        for (address, account) in &self.accounts {
+            program_test.add_account(*address, account.clone());
+        }
+
+        let (banks_client, payer, _) = program_test.start().await;
+        Ok((banks_client, payer))
+    }
+
+    /// Start a local `solana-test-validator` with the fixture, returning a nonblocking `RpcClient` and a funded payer.
+    /// The returned `TestValidator` must be kept alive for as long as the client is used.
+    #[cfg(feature = "test-validator")]
+    pub async fn start_test_validator(
+        &self,
+    ) -> Result<(RpcClient, Keypair, TestValidator), TestFrameWorkError> {
+        let mut genesis = TestValidatorGenesis::default();
+
+        for (program_id, program_path) in &self.programs {
+            if !program_path.exists() {
+                return Err(TestFrameWorkError::Io(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("program {} not found", program_path.display()),
+                )));
+            }
+
+            genesis.add_programs_with_path(&[ProgramInfo {
+                program_id: *program_id,
+                loader: bpf_loader::id(),
+                program_path: program_path.clone(),
+            }]);
+        }
+        for (address, account) in &self.accounts {
+            genesis.add_account(*address, account.clone().into());
+        }
+
+        let (test_validator, payer) = genesis.start_async().await;
+        Ok((test_validator.get_async_rpc_client(), payer, test_validator))
+    }
+}
+
+/// Run each test body twice, once against `ProgramTest` + `BanksClient` (test `<name>::banks_client`)
+/// and once against a local `solana-test-validator` + nonblocking `RpcClient` (test `<name>::test_validator`,
+/// requires the `test-validator` feature). Both start from the same [`Fixture`](crate::harness::Fixture).
+///
+/// `client` is a `&mut` to the backend client and implements both `ClientExtensions` and
+/// [`TestClient`](crate::harness::TestClient), `payer` is a funded `Keypair`.
+#[macro_export]
+macro_rules! backend_test {
+    (
+        fixture = $fixture:expr;
+        $($(#[$meta:meta])* async fn $name:ident($client:ident, $payer:ident) $body:block)*
+    ) => {
+        $(
+            mod $name {
+                use super::*;
+                use $crate::tokio;
+
+                $(#[$meta])*
+                #[tokio::test]
+                async fn banks_client() {
+                    let fixture: $crate::harness::Fixture = $fixture;
+                    let (mut client, $payer) = fixture.start_banks_client().await.unwrap();
+                    let $client = &mut client;
+                    $body
+                }
+
+                $crate::__backend_test_validator_case!([$(#[$meta])*] $fixture, $client, $payer, $body);
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "test-validator")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __backend_test_validator_case {
+    ([$(#[$meta:meta])*] $fixture:expr, $client:ident, $payer:ident, $body:block) => {
+        $(#[$meta])*
+        #[tokio::test]
+        async fn test_validator() {
+            let fixture: $crate::harness::Fixture = $fixture;
+            let (mut client, $payer, _test_validator) =
+                fixture.start_test_validator().await.unwrap();
+            let $client = &mut client;
+            $body
+        }
+    };
+}
+
+#[cfg(not(feature = "test-validator"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __backend_test_validator_case {
+    ($($tokens:tt)*) => {};
+}
@@ -0,0 +1,386 @@
+use crate::error::TestFrameWorkError;
+use anchor_lang_idl_spec::{
+    IdlArrayLen, IdlDefinedFields, IdlSerialization, IdlType, IdlTypeDef, IdlTypeDefTy,
+};
+use serde_json::{Map, Value};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+/// Serialize a JSON value with the Borsh layout of an IDL type.
+/// Integers are JSON numbers or decimal strings, public keys and byte arrays base58 strings or
+/// arrays of numbers, enum variants their name or `{ "Variant": fields }` for variants with fields.
+pub(crate) fn encode_value(
+    types: &[IdlTypeDef],
+    ty: &IdlType,
+    value: &Value,
+    data: &mut Vec<u8>,
+) -> Result<(), TestFrameWorkError> {
+    match ty {
+        IdlType::Bool => data.push(value.as_bool().ok_or_else(|| mismatch(ty, value))? as u8),
+        IdlType::U8 => data.extend(integer::<u8>(ty, value)?.to_le_bytes()),
+        IdlType::I8 => data.extend(integer::<i8>(ty, value)?.to_le_bytes()),
+        IdlType::U16 => data.extend(integer::<u16>(ty, value)?.to_le_bytes()),
+        IdlType::I16 => data.extend(integer::<i16>(ty, value)?.to_le_bytes()),
+        IdlType::U32 => data.extend(integer::<u32>(ty, value)?.to_le_bytes()),
+        IdlType::I32 => data.extend(integer::<i32>(ty, value)?.to_le_bytes()),
+        IdlType::U64 => data.extend(integer::<u64>(ty, value)?.to_le_bytes()),
+        IdlType::I64 => data.extend(integer::<i64>(ty, value)?.to_le_bytes()),
+        IdlType::U128 => data.extend(integer::<u128>(ty, value)?.to_le_bytes()),
+        IdlType::I128 => data.extend(integer::<i128>(ty, value)?.to_le_bytes()),
+        IdlType::F32 => {
+            let float = value.as_f64().ok_or_else(|| mismatch(ty, value))?;
+            data.extend((float as f32).to_le_bytes());
+        }
+        IdlType::F64 => {
+            let float = value.as_f64().ok_or_else(|| mismatch(ty, value))?;
+            data.extend(float.to_le_bytes());
+        }
+        IdlType::Bytes => {
+            let bytes = bytes(ty, value)?;
+            data.extend((bytes.len() as u32).to_le_bytes());
+            data.extend(bytes);
+        }
+        IdlType::String => {
+            let string = value.as_str().ok_or_else(|| mismatch(ty, value))?;
+            data.extend((string.len() as u32).to_le_bytes());
+            data.extend(string.as_bytes());
+        }
+        IdlType::Pubkey => data.extend(pubkey(value)?.to_bytes()),
+        IdlType::Option(inner) => {
+            if value.is_null() {
+                data.push(0);
+            } else {
+                data.push(1);
+                encode_value(types, inner, value, data)?;
+            }
+        }
+        IdlType::Vec(inner) => {
+            let items = value.as_array().ok_or_else(|| mismatch(ty, value))?;
+            data.extend((items.len() as u32).to_le_bytes());
+            for item in items {
+                encode_value(types, inner, item, data)?;
+            }
+        }
+        IdlType::Array(inner, IdlArrayLen::Value(len)) => {
+            // byte arrays such as seeds or hashes can also be given as base58 strings
+            if **inner == IdlType::U8 && value.is_string() {
+                let bytes = bytes(ty, value)?;
+                if bytes.len() != *len {
+                    return Err(mismatch(ty, value));
+                }
+                data.extend(bytes);
+                return Ok(());
+            }
+
+            let items = value.as_array().ok_or_else(|| mismatch(ty, value))?;
+            if items.len() != *len {
+                return Err(mismatch(ty, value));
+            }
+            for item in items {
+                encode_value(types, inner, item, data)?;
+            }
+        }
+        IdlType::Defined { name, generics } if generics.is_empty() => {
+            encode_defined(types, type_def(types, name)?, value, data)?;
+        }
+        _ => return Err(unsupported(ty)),
+    }
+
+    Ok(())
+}
+
+/// Serialize a value of a type defined in the IDL, only Borsh serialized types without generics are supported.
+pub(crate) fn encode_defined(
+    types: &[IdlTypeDef],
+    type_def: &IdlTypeDef,
+    value: &Value,
+    data: &mut Vec<u8>,
+) -> Result<(), TestFrameWorkError> {
+    check_supported(type_def)?;
+
+    match &type_def.ty {
+        IdlTypeDefTy::Struct { fields } => encode_fields(types, fields.as_ref(), value, data),
+        IdlTypeDefTy::Enum { variants } => {
+            let (name, fields) = match value {
+                Value::String(name) => (name.as_str(), &Value::Null),
+                Value::Object(variant) if variant.len() == 1 => {
+                    let (name, fields) = variant.iter().next().unwrap();
+                    (name.as_str(), fields)
+                }
+                _ => {
+                    return Err(TestFrameWorkError::Idl(format!(
+                        "expected a variant of {}, got {}",
+                        type_def.name, value
+                    )))
+                }
+            };
+            let index = variants
+                .iter()
+                .position(|variant| variant.name == name)
+                .ok_or_else(|| {
+                    TestFrameWorkError::Idl(format!("{} has no variant {}", type_def.name, name))
+                })?;
+
+            data.push(index as u8);
+            encode_fields(types, variants[index].fields.as_ref(), fields, data)
+        }
+        IdlTypeDefTy::Type { alias } => encode_value(types, alias, value, data),
+    }
+}
+
+/// Serialize named fields from a JSON object and tuple fields from a JSON array.
+fn encode_fields(
+    types: &[IdlTypeDef],
+    fields: Option<&IdlDefinedFields>,
+    value: &Value,
+    data: &mut Vec<u8>,
+) -> Result<(), TestFrameWorkError> {
+    match fields {
+        None => Ok(()),
+        Some(IdlDefinedFields::Named(fields)) => {
+            for field in fields {
+                let field_value = value.get(&field.name).ok_or_else(|| {
+                    TestFrameWorkError::Idl(format!("missing field {} in {}", field.name, value))
+                })?;
+                encode_value(types, &field.ty, field_value, data)?;
+            }
+            Ok(())
+        }
+        Some(IdlDefinedFields::Tuple(tys)) => {
+            let items = value
+                .as_array()
+                .filter(|items| items.len() == tys.len())
+                .ok_or_else(|| {
+                    TestFrameWorkError::Idl(format!(
+                        "expected {} tuple fields, got {}",
+                        tys.len(),
+                        value
+                    ))
+                })?;
+            for (ty, item) in tys.iter().zip(items) {
+                encode_value(types, ty, item, data)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Deserialize the Borsh layout of an IDL type into the JSON format `encode_value` accepts,
+/// with 128 bit integers as decimal strings and bytes as arrays of numbers.
+pub(crate) fn decode_value(
+    types: &[IdlTypeDef],
+    ty: &IdlType,
+    data: &mut &[u8],
+) -> Result<Value, TestFrameWorkError> {
+    let value = match ty {
+        IdlType::Bool => match take::<1>(data)? {
+            [0] => Value::Bool(false),
+            [1] => Value::Bool(true),
+            [byte] => {
+                return Err(TestFrameWorkError::Idl(format!(
+                    "invalid bool value {}",
+                    byte
+                )))
+            }
+        },
+        IdlType::U8 => u8::from_le_bytes(take(data)?).into(),
+        IdlType::I8 => i8::from_le_bytes(take(data)?).into(),
+        IdlType::U16 => u16::from_le_bytes(take(data)?).into(),
+        IdlType::I16 => i16::from_le_bytes(take(data)?).into(),
+        IdlType::U32 => u32::from_le_bytes(take(data)?).into(),
+        IdlType::I32 => i32::from_le_bytes(take(data)?).into(),
+        IdlType::U64 => u64::from_le_bytes(take(data)?).into(),
+        IdlType::I64 => i64::from_le_bytes(take(data)?).into(),
+        IdlType::U128 => u128::from_le_bytes(take(data)?).to_string().into(),
+        IdlType::I128 => i128::from_le_bytes(take(data)?).to_string().into(),
+        IdlType::F32 => f32::from_le_bytes(take(data)?).into(),
+        IdlType::F64 => f64::from_le_bytes(take(data)?).into(),
+        IdlType::Bytes => {
+            let len = length(data)?;
+            take_slice(data, len)?.to_vec().into()
+        }
+        IdlType::String => {
+            let len = length(data)?;
+            String::from_utf8(take_slice(data, len)?.to_vec())
+                .map_err(|_| TestFrameWorkError::Idl("invalid UTF-8 string".to_string()))?
+                .into()
+        }
+        IdlType::Pubkey => Pubkey::new_from_array(take(data)?).to_string().into(),
+        IdlType::Option(inner) => match take::<1>(data)? {
+            [0] => Value::Null,
+            [1] => decode_value(types, inner, data)?,
+            [byte] => {
+                return Err(TestFrameWorkError::Idl(format!(
+                    "invalid option tag {}",
+                    byte
+                )))
+            }
+        },
+        IdlType::Vec(inner) => {
+            let len = length(data)?;
+            (0..len)
+                .map(|_| decode_value(types, inner, data))
+                .collect::<Result<Vec<_>, _>>()?
+                .into()
+        }
+        IdlType::Array(inner, IdlArrayLen::Value(len)) => (0..*len)
+            .map(|_| decode_value(types, inner, data))
+            .collect::<Result<Vec<_>, _>>()?
+            .into(),
+        IdlType::Defined { name, generics } if generics.is_empty() => {
+            decode_defined(types, type_def(types, name)?, data)?
+        }
+        _ => return Err(unsupported(ty)),
+    };
+
+    Ok(value)
+}
+
+/// Deserialize a value of a type defined in the IDL.
+pub(crate) fn decode_defined(
+    types: &[IdlTypeDef],
+    type_def: &IdlTypeDef,
+    data: &mut &[u8],
+) -> Result<Value, TestFrameWorkError> {
+    check_supported(type_def)?;
+
+    match &type_def.ty {
+        IdlTypeDefTy::Struct { fields } => decode_fields(types, fields.as_ref(), data),
+        IdlTypeDefTy::Enum { variants } => {
+            let [index] = take::<1>(data)?;
+            let variant = variants.get(index as usize).ok_or_else(|| {
+                TestFrameWorkError::Idl(format!("{} has no variant {}", type_def.name, index))
+            })?;
+
+            match &variant.fields {
+                None => Ok(Value::String(variant.name.clone())),
+                Some(fields) => {
+                    let mut value = Map::new();
+                    value.insert(
+                        variant.name.clone(),
+                        decode_fields(types, Some(fields), data)?,
+                    );
+                    Ok(Value::Object(value))
+                }
+            }
+        }
+        IdlTypeDefTy::Type { alias } => decode_value(types, alias, data),
+    }
+}
+
+/// Deserialize named fields into a JSON object and tuple fields into a JSON array.
+fn decode_fields(
+    types: &[IdlTypeDef],
+    fields: Option<&IdlDefinedFields>,
+    data: &mut &[u8],
+) -> Result<Value, TestFrameWorkError> {
+    match fields {
+        None => Ok(Value::Object(Map::new())),
+        Some(IdlDefinedFields::Named(fields)) => {
+            let mut value = Map::new();
+            for field in fields {
+                value.insert(field.name.clone(), decode_value(types, &field.ty, data)?);
+            }
+            Ok(Value::Object(value))
+        }
+        Some(IdlDefinedFields::Tuple(tys)) => tys
+            .iter()
+            .map(|ty| decode_value(types, ty, data))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array),
+    }
+}
+
+fn take<const N: usize>(data: &mut &[u8]) -> Result<[u8; N], TestFrameWorkError> {
+    // the slice has exactly N bytes
+    Ok(take_slice(data, N)?.try_into().unwrap())
+}
+
+fn take_slice<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8], TestFrameWorkError> {
+    if data.len() < len {
+        return Err(TestFrameWorkError::Idl(
+            "unexpected end of account data".to_string(),
+        ));
+    }
+    let (bytes, rest) = data.split_at(len);
+    *data = rest;
+
+    Ok(bytes)
+}
+
+fn length(data: &mut &[u8]) -> Result<usize, TestFrameWorkError> {
+    Ok(u32::from_le_bytes(take(data)?) as usize)
+}
+
+/// Find a type defined in the IDL by name.
+pub(crate) fn type_def<'a>(
+    types: &'a [IdlTypeDef],
+    name: &str,
+) -> Result<&'a IdlTypeDef, TestFrameWorkError> {
+    types
+        .iter()
+        .find(|type_def| type_def.name == name)
+        .ok_or_else(|| TestFrameWorkError::Idl(format!("type {} is not defined in the IDL", name)))
+}
+
+fn check_supported(type_def: &IdlTypeDef) -> Result<(), TestFrameWorkError> {
+    if !matches!(type_def.serialization, IdlSerialization::Borsh) {
+        return Err(TestFrameWorkError::Idl(format!(
+            "{} uses {:?} serialization, only Borsh is supported",
+            type_def.name, type_def.serialization
+        )));
+    }
+    if !type_def.generics.is_empty() {
+        return Err(TestFrameWorkError::Idl(format!(
+            "{} has generics, which are not supported",
+            type_def.name
+        )));
+    }
+
+    Ok(())
+}
+
+fn integer<T: FromStr + TryFrom<u64> + TryFrom<i64>>(
+    ty: &IdlType,
+    value: &Value,
+) -> Result<T, TestFrameWorkError> {
+    let integer = match value {
+        Value::Number(number) => number
+            .as_u64()
+            .and_then(|number| T::try_from(number).ok())
+            .or_else(|| number.as_i64().and_then(|number| T::try_from(number).ok())),
+        Value::String(string) => string.parse().ok(),
+        _ => None,
+    };
+
+    integer.ok_or_else(|| mismatch(ty, value))
+}
+
+/// Read bytes given as a base58 string or as an array of numbers.
+fn bytes(ty: &IdlType, value: &Value) -> Result<Vec<u8>, TestFrameWorkError> {
+    match value {
+        Value::String(string) => solana_sdk::bs58::decode(string)
+            .into_vec()
+            .map_err(|_| mismatch(ty, value)),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| integer::<u8>(&IdlType::U8, item))
+            .collect(),
+        _ => Err(mismatch(ty, value)),
+    }
+}
+
+pub(crate) fn pubkey(value: &Value) -> Result<Pubkey, TestFrameWorkError> {
+    value
+        .as_str()
+        .and_then(|string| Pubkey::from_str(string).ok())
+        .ok_or_else(|| mismatch(&IdlType::Pubkey, value))
+}
+
+fn mismatch(ty: &IdlType, value: &Value) -> TestFrameWorkError {
+    TestFrameWorkError::Idl(format!("expected a value of type {:?}, got {}", ty, value))
+}
+
+fn unsupported(ty: &IdlType) -> TestFrameWorkError {
+    TestFrameWorkError::Idl(format!("type {:?} is not supported", ty))
+}
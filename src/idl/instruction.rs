@@ -0,0 +1,306 @@
+use super::{codec, AnchorIdl};
+use crate::error::TestFrameWorkError;
+use anchor_lang_idl_spec::{
+    IdlDefinedFields, IdlField, IdlInstruction, IdlInstructionAccount, IdlInstructionAccountItem,
+    IdlSeed, IdlType, IdlTypeDef, IdlTypeDefTy,
+};
+use serde_json::{Map, Value};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Builds an instruction of an [`AnchorIdl`] from JSON arguments and named accounts.
+///
+/// Accounts that are not given are resolved from the IDL: fixed addresses, PDAs whose seeds are
+/// constants, arguments or other accounts, and optional accounts, which default to the program id.
+/// Accounts of nested account structs are named `outer.inner`.
+pub struct IdlInstructionBuilder<'a> {
+    idl: &'a AnchorIdl,
+    instruction: &'a IdlInstruction,
+    args: Map<String, Value>,
+    invalid_args: Option<Value>,
+    accounts: HashMap<String, Pubkey>,
+}
+
+impl<'a> IdlInstructionBuilder<'a> {
+    pub(crate) fn new(idl: &'a AnchorIdl, instruction: &'a IdlInstruction) -> Self {
+        Self {
+            idl,
+            instruction,
+            args: Map::new(),
+            invalid_args: None,
+            accounts: HashMap::new(),
+        }
+    }
+
+    /// Set the arguments from a JSON object keyed by argument name.
+    /// Any other JSON value makes `build` fail.
+    pub fn args(mut self, args: Value) -> Self {
+        match args {
+            Value::Object(args) => self.args.extend(args),
+            args => self.invalid_args = Some(args),
+        }
+        self
+    }
+
+    /// Set a single argument.
+    pub fn arg(mut self, name: &str, value: Value) -> Self {
+        self.args.insert(name.to_string(), value);
+        self
+    }
+
+    /// Set the address of an account, overriding any address resolved from the IDL.
+    pub fn account(mut self, name: &str, address: Pubkey) -> Self {
+        self.accounts.insert(name.to_string(), address);
+        self
+    }
+
+    /// Encode the arguments after the instruction discriminator and resolve the accounts.
+    pub fn build(self) -> Result<Instruction, TestFrameWorkError> {
+        if let Some(args) = &self.invalid_args {
+            return Err(TestFrameWorkError::Idl(format!(
+                "arguments of instruction {} must be a JSON object, got {}",
+                self.instruction.name, args
+            )));
+        }
+
+        let types = &self.idl.spec().types;
+        let mut data = self.instruction.discriminator.clone();
+        for arg in &self.instruction.args {
+            codec::encode_value(types, &arg.ty, self.arg_value(&arg.name)?, &mut data)?;
+        }
+
+        let mut accounts = Vec::new();
+        flatten_accounts("", &self.instruction.accounts, &mut accounts);
+
+        if let Some(name) = self.accounts.keys().find(|name| {
+            !accounts
+                .iter()
+                .any(|(account_name, _)| account_name == *name)
+        }) {
+            return Err(TestFrameWorkError::Idl(format!(
+                "instruction {} has no account {}",
+                self.instruction.name, name
+            )));
+        }
+
+        let mut addresses = accounts
+            .iter()
+            .map(|(name, account)| match self.accounts.get(name) {
+                Some(address) => Ok(Some(*address)),
+                None => account
+                    .address
+                    .as_ref()
+                    .map(|address| {
+                        Pubkey::from_str(address).map_err(|_| {
+                            TestFrameWorkError::Idl(format!(
+                                "invalid address {} of account {}",
+                                address, name
+                            ))
+                        })
+                    })
+                    .transpose(),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // PDAs can be derived from other PDAs, so resolve until no more accounts can be derived
+        loop {
+            let mut progress = false;
+            for (index, (name, account)) in accounts.iter().enumerate() {
+                if addresses[index].is_some() {
+                    continue;
+                }
+                if let Some(address) = self.derive_pda(name, account, &accounts, &addresses)? {
+                    addresses[index] = Some(address);
+                    progress = true;
+                }
+            }
+            if !progress {
+                break;
+            }
+        }
+
+        let account_metas = accounts
+            .iter()
+            .zip(addresses)
+            .map(|((name, account), address)| match address {
+                Some(address) if account.writable => Ok(AccountMeta::new(address, account.signer)),
+                Some(address) => Ok(AccountMeta::new_readonly(address, account.signer)),
+                None if account.optional => {
+                    Ok(AccountMeta::new_readonly(self.idl.program_id(), false))
+                }
+                None => Err(TestFrameWorkError::Idl(format!(
+                    "account {} of instruction {} is missing",
+                    name, self.instruction.name
+                ))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Instruction {
+            program_id: self.idl.program_id(),
+            accounts: account_metas,
+            data,
+        })
+    }
+
+    fn arg_value(&self, name: &str) -> Result<&Value, TestFrameWorkError> {
+        self.args.get(name).ok_or_else(|| {
+            TestFrameWorkError::Idl(format!(
+                "argument {} of instruction {} is missing",
+                name, self.instruction.name
+            ))
+        })
+    }
+
+    /// Derive the address of a PDA account, `None` if it depends on an account that is not resolved yet.
+    fn derive_pda(
+        &self,
+        name: &str,
+        account: &IdlInstructionAccount,
+        accounts: &[(String, &IdlInstructionAccount)],
+        addresses: &[Option<Pubkey>],
+    ) -> Result<Option<Pubkey>, TestFrameWorkError> {
+        let pda = match &account.pda {
+            Some(pda) => pda,
+            None => return Ok(None),
+        };
+        // seeds referencing accounts are relative to the account struct the PDA is declared in
+        let prefix = match name.rfind('.') {
+            Some(index) => &name[..=index],
+            None => "",
+        };
+
+        let mut seeds = Vec::with_capacity(pda.seeds.len());
+        for seed in &pda.seeds {
+            match self.seed_bytes(seed, prefix, accounts, addresses)? {
+                Some(seed) => seeds.push(seed),
+                None => return Ok(None),
+            }
+        }
+        let program_id = match &pda.program {
+            Some(program) => match self.seed_bytes(program, prefix, accounts, addresses)? {
+                Some(program) => Pubkey::try_from(program.as_slice()).map_err(|_| {
+                    TestFrameWorkError::Idl(format!("invalid PDA program of account {}", name))
+                })?,
+                None => return Ok(None),
+            },
+            None => self.idl.program_id(),
+        };
+
+        let seeds = seeds.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        Ok(Some(Pubkey::find_program_address(&seeds, &program_id).0))
+    }
+
+    fn seed_bytes(
+        &self,
+        seed: &IdlSeed,
+        prefix: &str,
+        accounts: &[(String, &IdlInstructionAccount)],
+        addresses: &[Option<Pubkey>],
+    ) -> Result<Option<Vec<u8>>, TestFrameWorkError> {
+        match seed {
+            IdlSeed::Const(seed) => Ok(Some(seed.value.clone())),
+            IdlSeed::Arg(seed) => self.arg_seed(&seed.path).map(Some),
+            IdlSeed::Account(seed) => {
+                let index = [format!("{}{}", prefix, seed.path), seed.path.clone()]
+                    .iter()
+                    .find_map(|path| accounts.iter().position(|(name, _)| name == path))
+                    .ok_or_else(|| {
+                        TestFrameWorkError::Idl(format!(
+                            "seeds from account data ({}) are not supported, pass the account instead",
+                            seed.path
+                        ))
+                    })?;
+
+                Ok(addresses[index].map(|address| address.to_bytes().to_vec()))
+            }
+        }
+    }
+
+    /// Encode the argument, or field of an argument, at `path` the way Anchor uses it as a seed:
+    /// strings and bytes as is, other types with their Borsh layout.
+    fn arg_seed(&self, path: &str) -> Result<Vec<u8>, TestFrameWorkError> {
+        let types = &self.idl.spec().types;
+        let mut fields = path.split('.');
+        let arg_name = fields.next().unwrap_or_default();
+        let arg = self
+            .instruction
+            .args
+            .iter()
+            .find(|arg| arg.name == arg_name)
+            .ok_or_else(|| {
+                TestFrameWorkError::Idl(format!("seed argument {} is not in the IDL", path))
+            })?;
+
+        let mut ty = &arg.ty;
+        let mut value = self.arg_value(arg_name)?;
+        for field_name in fields {
+            let field = defined_fields(types, ty)?
+                .iter()
+                .find(|field| field.name == field_name)
+                .ok_or_else(|| {
+                    TestFrameWorkError::Idl(format!("seed argument {} is not in the IDL", path))
+                })?;
+            ty = &field.ty;
+            value = value
+                .get(field_name)
+                .ok_or_else(|| TestFrameWorkError::Idl(format!("argument {} is missing", path)))?;
+        }
+
+        let mut seed = Vec::new();
+        match (ty, value) {
+            (IdlType::String, Value::String(string)) => seed.extend(string.as_bytes()),
+            (IdlType::Bytes, _) => {
+                codec::encode_value(types, ty, value, &mut seed)?;
+                seed.drain(..4);
+            }
+            _ => codec::encode_value(types, ty, value, &mut seed)?,
+        }
+
+        Ok(seed)
+    }
+}
+
+/// The named fields of a struct defined in the IDL.
+fn defined_fields<'a>(
+    types: &'a [IdlTypeDef],
+    ty: &IdlType,
+) -> Result<&'a [IdlField], TestFrameWorkError> {
+    if let IdlType::Defined { name, .. } = ty {
+        match &codec::type_def(types, name)?.ty {
+            IdlTypeDefTy::Struct {
+                fields: Some(IdlDefinedFields::Named(fields)),
+            } => return Ok(fields),
+            IdlTypeDefTy::Type { alias } => return defined_fields(types, alias),
+            _ => {}
+        }
+    }
+
+    Err(TestFrameWorkError::Idl(format!(
+        "type {:?} has no named fields",
+        ty
+    )))
+}
+
+/// List the accounts of an instruction in order, naming accounts of nested structs `outer.inner`.
+fn flatten_accounts<'a>(
+    prefix: &str,
+    items: &'a [IdlInstructionAccountItem],
+    accounts: &mut Vec<(String, &'a IdlInstructionAccount)>,
+) {
+    for item in items {
+        match item {
+            IdlInstructionAccountItem::Single(account) => {
+                accounts.push((format!("{}{}", prefix, account.name), account))
+            }
+            IdlInstructionAccountItem::Composite(composite) => flatten_accounts(
+                &format!("{}{}.", prefix, composite.name),
+                &composite.accounts,
+                accounts,
+            ),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Build instructions for Anchor programs from their IDL, without depending on the program crate.
+
+mod codec;
+mod instruction;
+
+pub use anchor_lang_idl_spec as spec;
+pub use instruction::*;
+
+use crate::error::TestFrameWorkError;
+use anchor_lang_idl_spec::Idl;
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+/// An Anchor IDL (0.30 format) and the address of the program it describes.
+#[derive(Debug, Clone)]
+pub struct AnchorIdl {
+    idl: Idl,
+    program_id: Pubkey,
+}
+
+impl AnchorIdl {
+    /// Load an IDL from a JSON file, such as `target/idl/<program>.json`.
+    pub fn from_file(path: &str) -> Result<Self, TestFrameWorkError> {
+        let json = std::fs::read_to_string(path).map_err(|error| {
+            TestFrameWorkError::Io(std::io::Error::new(
+                error.kind(),
+                format!("{}: {}", path, error),
+            ))
+        })?;
+
+        Self::from_json(&json)
+    }
+
+    /// Parse an IDL from JSON, the program id is taken from its `address`.
+    pub fn from_json(json: &str) -> Result<Self, TestFrameWorkError> {
+        let idl: Idl = serde_json::from_str(json)
+            .map_err(|error| TestFrameWorkError::Idl(format!("invalid IDL: {}", error)))?;
+        let program_id = Pubkey::from_str(&idl.address).map_err(|_| {
+            TestFrameWorkError::Idl(format!("invalid program address {}", idl.address))
+        })?;
+
+        Ok(Self { idl, program_id })
+    }
+
+    /// Use a different program id than the `address` of the IDL, e.g. when the program is added under another address.
+    pub fn with_program_id(mut self, program_id: Pubkey) -> Self {
+        self.program_id = program_id;
+        self
+    }
+
+    pub fn program_id(&self) -> Pubkey {
+        self.program_id
+    }
+
+    /// The parsed IDL.
+    pub fn spec(&self) -> &Idl {
+        &self.idl
+    }
+
+    /// Start building the instruction with the given name, as written in the IDL.
+    pub fn instruction(&self, name: &str) -> Result<IdlInstructionBuilder<'_>, TestFrameWorkError> {
+        let instruction = self
+            .idl
+            .instructions
+            .iter()
+            .find(|instruction| instruction.name == name)
+            .ok_or_else(|| {
+                TestFrameWorkError::Idl(format!("instruction {} is not in the IDL", name))
+            })?;
+
+        Ok(IdlInstructionBuilder::new(self, instruction))
+    }
+
+    /// Serialize an account of the given type from JSON, prefixed with its discriminator.
+    pub fn encode_account(&self, name: &str, value: &Value) -> Result<Vec<u8>, TestFrameWorkError> {
+        let account = self
+            .idl
+            .accounts
+            .iter()
+            .find(|account| account.name == name)
+            .ok_or_else(|| {
+                TestFrameWorkError::Idl(format!("account {} is not in the IDL", name))
+            })?;
+
+        let mut data = account.discriminator.clone();
+        codec::encode_defined(
+            &self.idl.types,
+            codec::type_def(&self.idl.types, name)?,
+            value,
+            &mut data,
+        )?;
+
+        Ok(data)
+    }
+
+    /// Identify the type of an account by its discriminator and deserialize it into JSON.
+    /// Returns the name of the account type and its fields, bytes after the account data are ignored.
+    pub fn decode_account(&self, data: &[u8]) -> Result<(&str, Value), TestFrameWorkError> {
+        // prefer the longest discriminator, custom discriminators can be prefixes of each other
+        let account = self
+            .idl
+            .accounts
+            .iter()
+            .filter(|account| data.starts_with(&account.discriminator))
+            .max_by_key(|account| account.discriminator.len())
+            .ok_or_else(|| {
+                TestFrameWorkError::Idl("no account of the IDL has this discriminator".to_string())
+            })?;
+
+        let mut fields = &data[account.discriminator.len()..];
+        let value = codec::decode_defined(
+            &self.idl.types,
+            codec::type_def(&self.idl.types, &account.name)?,
+            &mut fields,
+        )?;
+
+        Ok((&account.name, value))
+    }
+}
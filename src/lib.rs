@@ -1,5 +1,9 @@
+pub mod assertions;
 pub mod error;
 mod extensions;
+pub mod harness;
+#[cfg(feature = "idl")]
+pub mod idl;
 pub mod util;
 
 pub use extensions::*;
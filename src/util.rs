@@ -1,9 +1,17 @@
+use crate::error::TestFrameWorkError;
+use solana_bpf_loader_program::syscalls::create_program_runtime_environment_v1;
+use solana_program_runtime::{
+    compute_budget::ComputeBudget,
+    invoke_context::InvokeContext,
+    solana_rbpf::{elf::Executable, verifier::RequisiteVerifier},
+};
 use solana_sdk::{
-    hash::Hash, instruction::Instruction, packet::PACKET_DATA_SIZE, signature::Signer,
-    transaction::Transaction,
+    feature_set::FeatureSet, hash::Hash, instruction::Instruction, packet::PACKET_DATA_SIZE,
+    signature::Signer, transaction::Transaction,
 };
 use std::fs::{metadata, File};
 use std::io::Read;
+use std::sync::Arc;
 
 #[cfg(feature = "pyth")]
 use {
@@ -21,6 +29,34 @@ pub fn load_file_to_bytes(filename: &str) -> (Vec<u8>, usize) {
     (buffer, metadata.len() as usize)
 }
 
+/// Read a program file, failing with an `Io` error naming the file instead of panicking.
+pub fn read_program_file(path_to_program: &str) -> Result<Vec<u8>, TestFrameWorkError> {
+    std::fs::read(path_to_program).map_err(|error| {
+        TestFrameWorkError::Io(std::io::Error::new(
+            error.kind(),
+            format!("{}: {}", path_to_program, error),
+        ))
+    })
+}
+
+/// Check that the program is an ELF the runtime accepts for deployment, with all features enabled.
+pub fn verify_elf(program_data: &[u8]) -> Result<(), TestFrameWorkError> {
+    let runtime_environment = create_program_runtime_environment_v1(
+        &FeatureSet::all_enabled(),
+        &ComputeBudget::default(),
+        true,
+        false,
+    )
+    .map_err(|error| TestFrameWorkError::InvalidProgram(error.to_string()))?;
+    let executable =
+        Executable::<InvokeContext>::from_elf(program_data, Arc::new(runtime_environment))
+            .map_err(|error| TestFrameWorkError::InvalidProgram(error.to_string()))?;
+
+    executable
+        .verify::<RequisiteVerifier>()
+        .map_err(|error| TestFrameWorkError::InvalidProgram(error.to_string()))
+}
+
 pub fn calculate_chunk_size<F: Fn(u32, Vec<u8>) -> Instruction>(
     deploy_ix: F,
     signers: &Vec<&dyn Signer>,
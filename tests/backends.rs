@@ -0,0 +1,140 @@
+use solana_test_framework::{harness::*, *};
+
+use {
+    solana_sdk::{
+        program_pack::Pack,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        system_transaction,
+    },
+    spl_token::state::{Account as TokenAccount, Mint},
+};
+
+use std::str::FromStr;
+
+fn fixture() -> Fixture {
+    Fixture::new().add_program(
+        Pubkey::from_str("CwrqeMj2U8tFr1Rhkgwc84tpAsqbt9pTt2a4taoTADPr").unwrap(),
+        "tests/artifacts/program_for_tests.so",
+    )
+}
+
+async fn transfer(client: &mut dyn TestClient, from: &Keypair, to: &Pubkey, lamports: u64) {
+    let latest_blockhash = client.fetch_latest_blockhash().await.unwrap();
+
+    client
+        .execute_transaction(system_transaction::transfer(
+            from,
+            to,
+            lamports,
+            latest_blockhash,
+        ))
+        .await
+        .unwrap();
+}
+
+backend_test! {
+    fixture = fixture();
+
+    async fn create_account(client, payer) {
+        let new_acc = Keypair::new();
+        let lamports = 1_000_000;
+
+        client
+            .create_account(&payer, &new_acc, lamports, 10, payer.pubkey())
+            .await
+            .unwrap();
+
+        let acc = client.fetch_account(new_acc.pubkey()).await.unwrap().unwrap();
+        assert_eq!(acc.lamports, lamports);
+        assert_eq!(acc.data.len(), 10);
+    }
+
+    async fn create_token_mint(client, payer) {
+        let mint = Keypair::new();
+        let freeze_pubkey = Pubkey::new_unique();
+        let decimals = 0;
+
+        client
+            .create_token_mint(
+                &mint,
+                &payer.pubkey(),
+                Some(&freeze_pubkey),
+                decimals,
+                &spl_token::id(),
+                &payer,
+            )
+            .await
+            .unwrap();
+
+        let mint_acc = client.fetch_account(mint.pubkey()).await.unwrap().unwrap();
+        let mint_data = Mint::unpack(&mint_acc.data).unwrap();
+
+        assert_eq!(mint_data.freeze_authority.unwrap(), freeze_pubkey);
+        assert_eq!(mint_data.decimals, decimals);
+        assert_eq!(mint_acc.owner, spl_token::id());
+    }
+
+    async fn create_token_account(client, payer) {
+        let mint = Keypair::new();
+        let token_account = Keypair::new();
+
+        client
+            .create_token_mint(&mint, &payer.pubkey(), None, 0, &spl_token::id(), &payer)
+            .await
+            .unwrap();
+        client
+            .create_token_account(
+                &token_account,
+                &payer.pubkey(),
+                &mint.pubkey(),
+                &spl_token::id(),
+                &payer,
+            )
+            .await
+            .unwrap();
+
+        let token_account = client
+            .fetch_account(token_account.pubkey())
+            .await
+            .unwrap()
+            .unwrap();
+        let token_account_data = TokenAccount::unpack(&token_account.data).unwrap();
+
+        assert_eq!(token_account_data.mint, mint.pubkey());
+        assert_eq!(token_account_data.owner, payer.pubkey());
+    }
+
+    async fn create_associated_token_account(client, payer) {
+        let mint = Keypair::new();
+
+        client
+            .create_token_mint(&mint, &payer.pubkey(), None, 0, &spl_token::id(), &payer)
+            .await
+            .unwrap();
+
+        let token_account = client
+            .create_associated_token_account(
+                &payer.pubkey(),
+                &mint.pubkey(),
+                &payer,
+                &spl_token::id(),
+            )
+            .await
+            .unwrap();
+
+        let token_account = client.fetch_account(token_account).await.unwrap().unwrap();
+        let token_account_data = TokenAccount::unpack(&token_account.data).unwrap();
+
+        assert_eq!(token_account_data.mint, mint.pubkey());
+        assert_eq!(token_account_data.owner, payer.pubkey());
+    }
+
+    async fn shared_helper(client, payer) {
+        let recipient = Pubkey::new_unique();
+
+        transfer(client, &payer, &recipient, 1_000_000).await;
+
+        assert_eq!(client.fetch_balance(recipient).await.unwrap(), 1_000_000);
+    }
+}
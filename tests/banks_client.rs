@@ -1,11 +1,14 @@
-use solana_test_framework::*;
+use solana_test_framework::{error::TestFrameWorkError, *};
 
 use {
     solana_sdk::{
+        bpf_loader_upgradeable::UpgradeableLoaderState,
+        compute_budget,
         program_pack::Pack,
         pubkey::Pubkey,
         signature::{Keypair, Signer},
         system_instruction,
+        transaction::Transaction,
     },
     spl_token::state::{Account as TokenAccount, Mint},
 };
@@ -56,6 +59,27 @@ async fn transaction_from_instructions() {
     assert_eq!(acc2_data.owner, acc_2.pubkey());
 }
 
+#[tokio::test]
+async fn transaction_from_instructions_missing_signer() {
+    let (mut program, _) = helpers::add_program();
+    let payer = helpers::add_payer(&mut program);
+    let new_acc = Keypair::new();
+    let (mut banks_client, _payer_keypair, mut _recent_blockhash) = program.start().await;
+    let ix = system_instruction::create_account(
+        &payer.pubkey(),
+        &new_acc.pubkey(),
+        1_000_000,
+        1,
+        &new_acc.pubkey(),
+    );
+
+    let result = banks_client
+        .transaction_from_instructions(&[ix], &payer, vec![&payer])
+        .await;
+
+    assert!(matches!(result, Err(TestFrameWorkError::Signer(_))));
+}
+
 #[tokio::test]
 async fn transaction_from_instructions_upgradeable() {
     let mut program = ProgramTest::default();
@@ -120,6 +144,55 @@ async fn get_account_with_anchor() {
     assert_eq!(count, anchor_acc_data.count)
 }
 
+#[cfg(feature = "anchor")]
+mod events {
+    use anchor_lang::prelude::*;
+
+    #[event]
+    pub struct CounterIncremented {
+        pub count: u64,
+    }
+}
+
+#[tokio::test]
+#[cfg(feature = "anchor")]
+async fn get_anchor_events() {
+    use anchor_lang::{event::EVENT_IX_TAG_LE, Event};
+    use base64::{prelude::BASE64_STANDARD, Engine};
+    use events::CounterIncremented;
+
+    let (program, _) = helpers::add_program();
+    let (mut banks_client, _payer_keypair, mut _recent_blockhash) = program.start().await;
+
+    let event_data = |count| CounterIncremented { count }.data();
+    let logs = vec![
+        "Program CwrqeMj2U8tFr1Rhkgwc84tpAsqbt9pTt2a4taoTADPr invoke [1]".to_string(),
+        format!("Program data: {}", BASE64_STANDARD.encode(event_data(1))),
+        // data logged by another program
+        format!("Program data: {}", BASE64_STANDARD.encode([0u8; 16])),
+        format!("Program data: {}", BASE64_STANDARD.encode(event_data(2))),
+    ];
+
+    let events: Vec<CounterIncremented> = banks_client
+        .get_anchor_events(logs.as_slice())
+        .await
+        .unwrap();
+    assert_eq!(
+        events.iter().map(|event| event.count).collect::<Vec<_>>(),
+        vec![1, 2]
+    );
+
+    // BanksClient does not keep transaction logs
+    assert!(banks_client
+        .get_anchor_events::<CounterIncremented, _>(solana_sdk::signature::Signature::default())
+        .await
+        .is_err());
+
+    let cpi_data = [EVENT_IX_TAG_LE.as_slice(), &event_data(3)].concat();
+    let events: Vec<CounterIncremented> = parse_anchor_cpi_events(&[cpi_data]).unwrap();
+    assert_eq!(events[0].count, 3);
+}
+
 #[tokio::test]
 async fn get_account_with_borsh() {
     let (mut program, program_id) = helpers::add_program();
@@ -137,112 +210,1039 @@ async fn get_account_with_borsh() {
 }
 
 #[tokio::test]
-async fn create_account() {
+async fn typed_errors() {
     let (mut program, _) = helpers::add_program();
     let payer = helpers::add_payer(&mut program);
-    let lamports = 1_000_000;
-    let new_acc = Keypair::new();
+    let missing = Pubkey::new_unique();
     let (mut banks_client, _payer_keypair, mut _recent_blockhash) = program.start().await;
+
+    let err = banks_client
+        .get_account_with_borsh::<program_for_tests::GreetingAccount>(missing)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, TestFrameWorkError::AccountNotFound(pubkey) if pubkey == missing));
+
+    // the payer cannot fund an account with more lamports than it holds
+    let err = banks_client
+        .create_account(&payer, &Keypair::new(), u64::MAX, 0, payer.pubkey())
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        TestFrameWorkError::Transaction {
+            ix_index: Some(0),
+            ..
+        }
+    ));
+}
+
+#[tokio::test]
+async fn build_transaction() {
+    let (mut program, _) = helpers::add_program();
+    let payer = helpers::add_payer(&mut program);
+    let recipient = Pubkey::new_unique();
+    let (mut banks_client, _payer_keypair, mut _recent_blockhash) = program.start().await;
+
+    let builder = TransactionBuilder::new(
+        &[system_instruction::transfer(
+            &payer.pubkey(),
+            &recipient,
+            1_000_000,
+        )],
+        &payer.pubkey(),
+    )
+    .compute_unit_limit(10_000)
+    .compute_unit_price(1)
+    .heap_frame(64 * 1024)
+    .memo("transfer");
+
+    let message = banks_client.build_message(&builder).await.unwrap();
+    assert_eq!(message.instructions.len(), 5);
+    for ix in &message.instructions[..3] {
+        assert_eq!(
+            message.account_keys[ix.program_id_index as usize],
+            compute_budget::id()
+        );
+    }
+    assert_eq!(
+        message.account_keys[message.instructions[4].program_id_index as usize],
+        spl_memo::id()
+    );
+
+    // signing fails if a required signer is missing
+    let err = banks_client
+        .build_transaction(&builder, &[])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, TestFrameWorkError::Signer(_)));
+
+    let unsigned = banks_client
+        .build_partially_signed_transaction(&builder, &[])
+        .await
+        .unwrap();
+    assert!(!unsigned.is_signed());
+
+    let tx = banks_client
+        .build_transaction(&builder, &[&payer])
+        .await
+        .unwrap();
+    banks_client.process_transaction(tx).await.unwrap();
+    assert_eq!(
+        banks_client.get_balance(recipient).await.unwrap(),
+        1_000_000
+    );
+}
+
+#[tokio::test]
+async fn build_transaction_with_nonce() {
+    let (mut program, _) = helpers::add_program();
+    let payer = helpers::add_payer(&mut program);
+    let nonce_account = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    let mut program_context = program.start_with_context().await;
+    let recent_blockhash = program_context.last_blockhash;
+
+    let create_nonce_tx = solana_sdk::transaction::Transaction::new_signed_with_payer(
+        &system_instruction::create_nonce_account(
+            &payer.pubkey(),
+            &nonce_account.pubkey(),
+            &payer.pubkey(),
+            10_000_000,
+        ),
+        Some(&payer.pubkey()),
+        &[&payer, &nonce_account],
+        recent_blockhash,
+    );
+    program_context
+        .banks_client
+        .process_transaction(create_nonce_tx)
+        .await
+        .unwrap();
+    // the nonce can only be advanced once the blockhash it stores is no longer the latest one
+    program_context.warp_to_slot(2).unwrap();
+
+    let builder = TransactionBuilder::new(
+        &[system_instruction::transfer(
+            &payer.pubkey(),
+            &recipient,
+            1_000_000,
+        )],
+        &payer.pubkey(),
+    )
+    .nonce(&nonce_account.pubkey(), &payer.pubkey());
+
+    let banks_client = &mut program_context.banks_client;
+    let tx = banks_client
+        .build_transaction(&builder, &[&payer])
+        .await
+        .unwrap();
+    assert_ne!(tx.message.recent_blockhash, recent_blockhash);
+
+    // durable nonce transactions are not in the recent blockhash queue, so they are processed directly on the bank
+    let processed = banks_client
+        .process_transaction_with_metadata(tx)
+        .await
+        .unwrap();
+    assert!(processed.result.is_ok());
+    assert_eq!(
+        banks_client.get_balance(recipient).await.unwrap(),
+        1_000_000
+    );
+}
+
+#[tokio::test]
+async fn versioned_transaction() {
+    let (mut program, _) = helpers::add_program();
+    let payer = helpers::add_payer(&mut program);
+    let lookup_table = Pubkey::new_unique();
+    // more accounts than fit in a legacy transaction
+    let recipients: Vec<Pubkey> = (0..40).map(|_| Pubkey::new_unique()).collect();
+    program.add_address_lookup_table(lookup_table, None, &recipients);
+    let mut program_context = program.start_with_context().await;
+    // addresses added to a table are usable from the slot after it was extended
+    program_context.warp_to_slot(2).unwrap();
+
+    let ixs: Vec<_> = recipients
+        .iter()
+        .map(|recipient| system_instruction::transfer(&payer.pubkey(), recipient, 1_000_000))
+        .collect();
+    let builder =
+        TransactionBuilder::new(&ixs, &payer.pubkey()).address_lookup_table(&lookup_table);
+
+    let banks_client = &mut program_context.banks_client;
+    let tx = banks_client
+        .build_versioned_transaction(&builder, &[&payer])
+        .await
+        .unwrap();
+    assert_eq!(tx.message.address_table_lookups().unwrap().len(), 1);
+
     banks_client
-        .create_account(&payer, &new_acc, lamports, 10, payer.pubkey())
+        .process_versioned_transaction(tx)
+        .await
+        .unwrap();
+    for recipient in recipients {
+        assert_eq!(
+            banks_client.get_balance(recipient).await.unwrap(),
+            1_000_000
+        );
+    }
+}
+
+#[tokio::test]
+async fn simulate_instructions() {
+    let (mut program, _) = helpers::add_program();
+    let payer = helpers::add_payer(&mut program);
+    let recipient = Pubkey::new_unique();
+    let (mut banks_client, _payer_keypair, mut _recent_blockhash) = program.start().await;
+
+    let simulation = banks_client
+        .simulate_instructions(
+            &[system_instruction::transfer(
+                &payer.pubkey(),
+                &recipient,
+                1_000_000,
+            )],
+            &payer,
+            vec![&payer],
+            &[],
+        )
         .await
         .unwrap();
+    assert!(simulation.result.is_ok());
+    assert!(simulation.compute_units_consumed > 0);
+    assert!(!simulation.logs.is_empty());
+    assert_eq!(simulation.post_accounts, None);
+    // nothing is committed
+    assert_eq!(banks_client.get_balance(recipient).await.unwrap(), 0);
 
-    let acc = banks_client
-        .get_account(new_acc.pubkey())
+    // account state cannot be reported
+    let ix = system_instruction::transfer(&payer.pubkey(), &recipient, 1_000_000);
+    assert!(banks_client
+        .simulate_instructions(&[ix], &payer, vec![&payer], &[recipient])
+        .await
+        .is_err());
+
+    let simulation = banks_client
+        .simulate_instructions(
+            &[system_instruction::transfer(
+                &payer.pubkey(),
+                &recipient,
+                u64::MAX,
+            )],
+            &payer,
+            vec![&payer],
+            &[],
+        )
+        .await
+        .unwrap();
+    assert!(simulation.result.is_err());
+}
+
+#[tokio::test]
+async fn assert_custom_error() {
+    let (mut program, _) = helpers::add_program();
+    let payer = helpers::add_payer(&mut program);
+    let (mut banks_client, _payer_keypair, mut _recent_blockhash) = program.start().await;
+
+    let result = banks_client
+        .create_account(&payer, &Keypair::new(), u64::MAX, 0, payer.pubkey())
+        .await;
+    assertions::assert_custom_error(
+        result,
+        0,
+        solana_sdk::system_instruction::SystemError::ResultWithNegativeLamports as u32,
+    );
+}
+
+#[cfg(feature = "anchor")]
+mod errors {
+    use anchor_lang::prelude::*;
+
+    #[error_code]
+    pub enum ErrorCode {
+        #[msg("You are not authorized to perform this action.")]
+        Unauthorized,
+    }
+}
+
+#[cfg(feature = "anchor")]
+fn unauthorized_failure(log: &str) -> Result<(), TestFrameWorkError> {
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    Err(TestFrameWorkError::transaction(
+        TransactionError::InstructionError(1, InstructionError::Custom(6000)),
+        vec![log.to_string()],
+    ))
+}
+
+#[test]
+#[cfg(feature = "anchor")]
+fn assert_anchor_error() {
+    assertions::assert_anchor_error(
+        unauthorized_failure("Program log: AnchorError occurred. Error Code: Unauthorized. Error Number: 6000. Error Message: You are not authorized to perform this action.."),
+        1,
+        errors::ErrorCode::Unauthorized,
+    );
+}
+
+#[test]
+#[cfg(feature = "anchor")]
+#[should_panic(expected = "expected the program to log")]
+fn assert_anchor_error_missing_log() {
+    assertions::assert_anchor_error(
+        unauthorized_failure("Program log: Instruction: Increment"),
+        1,
+        errors::ErrorCode::Unauthorized,
+    );
+}
+
+#[tokio::test]
+async fn get_program_accounts() {
+    use solana_test_framework::{Memcmp, RpcFilterType};
+
+    let (program, _) = helpers::add_program();
+    let mut program = TrackedProgramTest::from(program);
+    let payer = helpers::add_payer(&mut program);
+    let program_id = Pubkey::new_unique();
+    let seeded_small = Pubkey::new_unique();
+    let seeded_large = Pubkey::new_unique();
+    let added = Pubkey::new_unique();
+    program.add_account_with_data(seeded_small, program_id, &[1, 0, 0, 0], false);
+    program.add_account_with_data(seeded_large, program_id, &[2; 8], false);
+    program.add_account_with_data(Pubkey::new_unique(), payer.pubkey(), &[1, 0, 0, 0], false);
+    // accounts added with the plain `add_account` are found as well
+    program.add_account(
+        added,
+        solana_sdk::account::Account {
+            lamports: 1_000_000,
+            data: vec![2; 8],
+            owner: program_id,
+            ..Default::default()
+        },
+    );
+
+    let (mut banks_client, _payer_keypair, mut _recent_blockhash) = program.start().await;
+    // accounts created by transactions are found as well
+    let created = Keypair::new();
+    banks_client
+        .create_account(&payer, &created, 1_000_000, 4, program_id)
+        .await
+        .unwrap();
+
+    let addresses = |accounts: Vec<(Pubkey, solana_sdk::account::Account)>| {
+        let mut addresses: Vec<Pubkey> = accounts.into_iter().map(|(address, _)| address).collect();
+        addresses.sort();
+        addresses
+    };
+    let sorted = |mut addresses: Vec<Pubkey>| {
+        addresses.sort();
+        addresses
+    };
+
+    let all = banks_client
+        .get_program_accounts(&program_id, vec![])
+        .await
+        .unwrap();
+    assert_eq!(
+        addresses(all),
+        sorted(vec![seeded_small, seeded_large, added, created.pubkey()])
+    );
+
+    let small = banks_client
+        .get_program_accounts(&program_id, vec![RpcFilterType::DataSize(4)])
+        .await
+        .unwrap();
+    assert_eq!(
+        addresses(small),
+        sorted(vec![seeded_small, created.pubkey()])
+    );
+
+    let filters = vec![
+        RpcFilterType::DataSize(4),
+        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, vec![1])),
+    ];
+    let matching = banks_client
+        .get_program_accounts(&program_id, filters)
+        .await
+        .unwrap();
+    assert_eq!(addresses(matching), vec![seeded_small]);
+
+    // a plain `BanksClient` cannot enumerate accounts
+    assert!(banks_client
+        .into_inner()
+        .get_program_accounts(&program_id, vec![])
+        .await
+        .is_err());
+}
+
+#[cfg(feature = "anchor")]
+anchor_lang::declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+#[cfg(feature = "anchor")]
+mod positions {
+    use anchor_lang::prelude::*;
+
+    #[account]
+    pub struct Position {
+        pub owner: Pubkey,
+        pub size: u64,
+    }
+
+    #[account]
+    pub struct Order {
+        pub owner: Pubkey,
+        pub size: u64,
+    }
+}
+
+#[tokio::test]
+#[cfg(feature = "anchor")]
+async fn get_program_accounts_with_anchor() {
+    use positions::{Order, Position};
+    use solana_test_framework::{Memcmp, RpcFilterType};
+
+    let (program, _) = helpers::add_program();
+    let mut program = TrackedProgramTest::from(program);
+    let owner = Pubkey::new_unique();
+    let position = Pubkey::new_unique();
+    program.add_account_with_anchor(position, ID, Position { owner, size: 10 }, false);
+    program.add_account_with_anchor(
+        Pubkey::new_unique(),
+        ID,
+        Position {
+            owner: Pubkey::new_unique(),
+            size: 20,
+        },
+        false,
+    );
+    program.add_account_with_anchor(Pubkey::new_unique(), ID, Order { owner, size: 30 }, false);
+
+    let (mut banks_client, _payer_keypair, mut _recent_blockhash) = program.start().await;
+
+    let positions = banks_client
+        .get_program_accounts_with_anchor::<Position>(&ID, vec![])
+        .await
+        .unwrap();
+    assert_eq!(positions.len(), 2);
+
+    let owner_filter = RpcFilterType::Memcmp(Memcmp::new_raw_bytes(8, owner.to_bytes().to_vec()));
+    let positions = banks_client
+        .get_program_accounts_with_anchor::<Position>(&ID, vec![owner_filter])
+        .await
+        .unwrap();
+    assert_eq!(positions.len(), 1);
+    assert_eq!(positions[0].0, position);
+    assert_eq!(positions[0].1.size, 10);
+}
+
+#[tokio::test]
+async fn get_token_state() {
+    let (mut program, _) = helpers::add_program();
+    let payer = helpers::add_payer(&mut program);
+    let mint_pubkey = Pubkey::new_unique();
+    let account_pubkey = Pubkey::new_unique();
+    let uninitialized_pubkey = Pubkey::new_unique();
+    program.add_token_mint(mint_pubkey, None, 10, 6, None);
+    program.add_account_with_data(
+        uninitialized_pubkey,
+        spl_token::id(),
+        &[0; Mint::LEN],
+        false,
+    );
+    program.add_token_account(
+        account_pubkey,
+        mint_pubkey,
+        payer.pubkey(),
+        10,
+        None,
+        None,
+        0,
+        None,
+    );
+
+    let (mut banks_client, _payer_keypair, mut _recent_blockhash) = program.start().await;
+
+    let mint = banks_client
+        .get_account_with_pack::<Mint>(mint_pubkey)
         .await
-        .unwrap()
         .unwrap();
-    assert_eq!(acc.lamports, lamports);
+    assert_eq!(mint.decimals, 6);
+
+    // a zeroed mint is not initialized
+    assert!(matches!(
+        banks_client
+            .get_account_with_pack::<Mint>(uninitialized_pubkey)
+            .await,
+        Err(TestFrameWorkError::Deserialize { .. })
+    ));
+
+    let mint = banks_client.get_mint(mint_pubkey).await.unwrap();
+    assert_eq!(mint.token_program_id, spl_token::id());
+    assert_eq!(mint.base().supply, 10);
+    assert!(mint.extension_types.is_empty());
+
+    let account = banks_client
+        .get_token_account(account_pubkey)
+        .await
+        .unwrap();
+    assert_eq!(account.base().mint, mint_pubkey);
+    assert_eq!(
+        banks_client
+            .get_token_balance(account_pubkey)
+            .await
+            .unwrap(),
+        10
+    );
+
+    assert!(matches!(
+        banks_client.get_mint(account_pubkey).await,
+        Err(TestFrameWorkError::Deserialize { .. })
+    ));
 }
 
 #[tokio::test]
-async fn create_token_mint() {
+async fn get_token_2022_state() {
+    use spl_token_2022::extension::{
+        mint_close_authority::MintCloseAuthority, BaseStateWithExtensions, ExtensionType,
+    };
+
     let (mut program, _) = helpers::add_program();
     let payer = helpers::add_payer(&mut program);
+    let (mut banks_client, _payer_keypair, mut _recent_blockhash) = program.start().await;
+    let token_program_id = spl_token_2022::id();
+
     let mint = Keypair::new();
-    let freeze_pubkey = Pubkey::new_unique();
-    let decimals = 0;
+    let space = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&[
+        ExtensionType::MintCloseAuthority,
+    ])
+    .unwrap();
+    let ixs = [
+        system_instruction::create_account(
+            &payer.pubkey(),
+            &mint.pubkey(),
+            1_000_000_000,
+            space as u64,
+            &token_program_id,
+        ),
+        spl_token_2022::instruction::initialize_mint_close_authority(
+            &token_program_id,
+            &mint.pubkey(),
+            Some(&payer.pubkey()),
+        )
+        .unwrap(),
+        spl_token_2022::instruction::initialize_mint2(
+            &token_program_id,
+            &mint.pubkey(),
+            &payer.pubkey(),
+            None,
+            2,
+        )
+        .unwrap(),
+    ];
+    banks_client
+        .process_instructions(&ixs, &payer, &[&mint])
+        .await
+        .unwrap();
+    let account = banks_client
+        .create_associated_token_account(&payer.pubkey(), &mint.pubkey(), &payer, &token_program_id)
+        .await
+        .unwrap();
+    banks_client
+        .mint_to(
+            &token_program_id,
+            &mint.pubkey(),
+            &account,
+            &TokenAuthority::single(&payer),
+            25,
+            &payer,
+        )
+        .await
+        .unwrap();
+
+    let mint_state = banks_client.get_mint(mint.pubkey()).await.unwrap();
+    assert_eq!(mint_state.token_program_id, token_program_id);
+    assert_eq!(mint_state.base().decimals, 2);
+    assert_eq!(
+        mint_state.extension_types,
+        vec![ExtensionType::MintCloseAuthority]
+    );
+    let close_authority = mint_state
+        .state
+        .get_extension::<MintCloseAuthority>()
+        .unwrap();
+    assert_eq!(
+        Option::<Pubkey>::from(close_authority.close_authority),
+        Some(payer.pubkey())
+    );
+
+    let account_state = banks_client.get_token_account(account).await.unwrap();
+    assert_eq!(
+        account_state.extension_types,
+        vec![ExtensionType::ImmutableOwner]
+    );
+    assert_eq!(banks_client.get_token_balance(account).await.unwrap(), 25);
+}
+
+#[tokio::test]
+async fn token_operations() {
+    token_operations_with_program(spl_token::id()).await;
+}
+
+#[tokio::test]
+async fn token_2022_operations() {
+    token_operations_with_program(spl_token_2022::id()).await;
+}
 
+/// Run the token operations against the given token program.
+async fn token_operations_with_program(token_program_id: Pubkey) {
+    let (mut program, _) = helpers::add_program();
+    let payer = helpers::add_payer(&mut program);
     let (mut banks_client, _payer_keypair, mut _recent_blockhash) = program.start().await;
+    let owner = TokenAuthority::single(&payer);
 
-    //Create mint with defaults
+    let mint = Keypair::new();
+    let source = Keypair::new();
+    let destination = Keypair::new();
+    let delegate = Keypair::new();
     banks_client
         .create_token_mint(
             &mint,
             &payer.pubkey(),
-            Some(&freeze_pubkey),
-            decimals,
+            Some(&payer.pubkey()),
+            2,
+            &token_program_id,
+            &payer,
+        )
+        .await
+        .unwrap();
+    for account in [&source, &destination] {
+        banks_client
+            .create_token_account(
+                account,
+                &payer.pubkey(),
+                &mint.pubkey(),
+                &token_program_id,
+                &payer,
+            )
+            .await
+            .unwrap();
+    }
+
+    banks_client
+        .mint_to(
+            &token_program_id,
+            &mint.pubkey(),
+            &source.pubkey(),
+            &owner,
+            1_000,
             &payer,
         )
         .await
         .unwrap();
-    //Test mint with defaults creation
-    let mint_acc = banks_client
-        .get_account(mint.pubkey())
+    banks_client
+        .transfer(
+            &token_program_id,
+            &source.pubkey(),
+            &destination.pubkey(),
+            &owner,
+            100,
+            &payer,
+        )
+        .await
+        .unwrap();
+    banks_client
+        .transfer_checked(
+            &token_program_id,
+            &source.pubkey(),
+            &mint.pubkey(),
+            &destination.pubkey(),
+            &owner,
+            100,
+            2,
+            &payer,
+        )
+        .await
+        .unwrap();
+    banks_client
+        .burn(
+            &token_program_id,
+            &source.pubkey(),
+            &mint.pubkey(),
+            &owner,
+            300,
+            &payer,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        banks_client
+            .get_token_balance(source.pubkey())
+            .await
+            .unwrap(),
+        500
+    );
+    assert_eq!(
+        banks_client
+            .get_token_balance(destination.pubkey())
+            .await
+            .unwrap(),
+        200
+    );
+
+    // the delegate can spend up to the approved amount
+    banks_client
+        .approve(
+            &token_program_id,
+            &source.pubkey(),
+            &delegate.pubkey(),
+            &owner,
+            50,
+            &payer,
+        )
+        .await
+        .unwrap();
+    banks_client
+        .transfer(
+            &token_program_id,
+            &source.pubkey(),
+            &destination.pubkey(),
+            &TokenAuthority::single(&delegate),
+            50,
+            &payer,
+        )
+        .await
+        .unwrap();
+    banks_client
+        .approve(
+            &token_program_id,
+            &source.pubkey(),
+            &delegate.pubkey(),
+            &owner,
+            50,
+            &payer,
+        )
+        .await
+        .unwrap();
+    banks_client
+        .revoke(&token_program_id, &source.pubkey(), &owner, &payer)
+        .await
+        .unwrap();
+    assert!(banks_client
+        .transfer(
+            &token_program_id,
+            &source.pubkey(),
+            &destination.pubkey(),
+            &TokenAuthority::single(&delegate),
+            50,
+            &payer,
+        )
+        .await
+        .is_err());
+
+    // frozen accounts cannot send tokens until thawed
+    banks_client
+        .freeze_account(
+            &token_program_id,
+            &source.pubkey(),
+            &mint.pubkey(),
+            &owner,
+            &payer,
+        )
+        .await
+        .unwrap();
+    assert!(banks_client
+        .transfer(
+            &token_program_id,
+            &source.pubkey(),
+            &destination.pubkey(),
+            &owner,
+            1,
+            &payer,
+        )
+        .await
+        .is_err());
+    banks_client
+        .thaw_account(
+            &token_program_id,
+            &source.pubkey(),
+            &mint.pubkey(),
+            &owner,
+            &payer,
+        )
         .await
-        .unwrap()
         .unwrap();
-    let mint_data = Mint::unpack(&mint_acc.data).unwrap();
-    assert_eq!(mint_data.freeze_authority.unwrap(), freeze_pubkey);
-    assert_eq!(mint_data.decimals, decimals);
-    assert_eq!(mint_acc.owner, spl_token::id());
+
+    banks_client
+        .burn(
+            &token_program_id,
+            &source.pubkey(),
+            &mint.pubkey(),
+            &owner,
+            450,
+            &payer,
+        )
+        .await
+        .unwrap();
+    banks_client
+        .close_account(
+            &token_program_id,
+            &source.pubkey(),
+            &payer.pubkey(),
+            &owner,
+            &payer,
+        )
+        .await
+        .unwrap();
+    assert!(banks_client
+        .get_account(source.pubkey())
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn token_multisig_authority() {
+    token_multisig_authority_with_program(spl_token::id()).await;
 }
 
 #[tokio::test]
-async fn create_token_account() {
+async fn token_2022_multisig_authority() {
+    token_multisig_authority_with_program(spl_token_2022::id()).await;
+}
+
+/// Mint with a multisig authority of the given token program.
+async fn token_multisig_authority_with_program(token_program_id: Pubkey) {
     let (mut program, _) = helpers::add_program();
     let payer = helpers::add_payer(&mut program);
-    let token_account = Keypair::new();
-    let mint_pubkey = Pubkey::new_unique();
-    //Create mint with defaults
-    program.add_token_mint(mint_pubkey, None, 10, 0, None);
-
     let (mut banks_client, _payer_keypair, mut _recent_blockhash) = program.start().await;
 
+    let mint = Keypair::new();
+    let account = Keypair::new();
+    let multisig = Keypair::new();
+    let members = [Keypair::new(), Keypair::new(), Keypair::new()];
+    banks_client
+        .create_token_mint(&mint, &payer.pubkey(), None, 0, &token_program_id, &payer)
+        .await
+        .unwrap();
+    banks_client
+        .create_token_account(
+            &account,
+            &payer.pubkey(),
+            &mint.pubkey(),
+            &token_program_id,
+            &payer,
+        )
+        .await
+        .unwrap();
+    banks_client
+        .create_token_multisig(
+            &multisig,
+            &members
+                .iter()
+                .map(|member| member.pubkey())
+                .collect::<Vec<_>>()
+                .iter()
+                .collect::<Vec<_>>(),
+            2,
+            &token_program_id,
+            &payer,
+        )
+        .await
+        .unwrap();
+
     banks_client
-        .create_token_account(&token_account, &payer.pubkey(), &mint_pubkey, &payer)
+        .set_authority(
+            &token_program_id,
+            &mint.pubkey(),
+            Some(&multisig.pubkey()),
+            spl_token_2022::instruction::AuthorityType::MintTokens,
+            &TokenAuthority::single(&payer),
+            &payer,
+        )
         .await
         .unwrap();
 
-    let token_account = banks_client
-        .get_account(token_account.pubkey())
+    // one member is not enough
+    assert!(banks_client
+        .mint_to(
+            &token_program_id,
+            &mint.pubkey(),
+            &account.pubkey(),
+            &TokenAuthority::multisig(&multisig.pubkey(), &[&members[0]]),
+            10,
+            &payer,
+        )
+        .await
+        .is_err());
+    banks_client
+        .mint_to(
+            &token_program_id,
+            &mint.pubkey(),
+            &account.pubkey(),
+            &TokenAuthority::multisig(&multisig.pubkey(), &[&members[0], &members[2]]),
+            10,
+            &payer,
+        )
         .await
-        .unwrap()
         .unwrap();
 
-    let token_account_data = TokenAccount::unpack(&token_account.data).unwrap();
+    assert_eq!(
+        banks_client
+            .get_token_balance(account.pubkey())
+            .await
+            .unwrap(),
+        10
+    );
+}
 
-    assert_eq!(token_account_data.mint, mint_pubkey);
-    assert_eq!(token_account_data.owner, payer.pubkey());
+#[tokio::test]
+async fn wrap_and_unwrap_sol() {
+    wrap_and_unwrap_sol_with_program(spl_token::id()).await;
 }
 
 #[tokio::test]
-async fn create_associated_token_account() {
+async fn wrap_and_unwrap_sol_2022() {
+    wrap_and_unwrap_sol_with_program(spl_token_2022::id()).await;
+}
+
+async fn wrap_and_unwrap_sol_with_program(token_program_id: Pubkey) {
     let (mut program, _) = helpers::add_program();
     let payer = helpers::add_payer(&mut program);
-    let mint_pubkey = Pubkey::new_unique();
-    let token_program_id = spl_token::ID; //could also use token-2022 ID
-                                          //Create mint with defaults
-    program.add_token_mint(mint_pubkey, None, 10, 0, None);
-
+    if token_program_id == spl_token_2022::id() {
+        // ProgramTest does not genesis the Token-2022 native mint
+        program.add_account_with_packable(
+            spl_token_2022::native_mint::id(),
+            spl_token_2022::id(),
+            spl_token_2022::state::Mint {
+                decimals: spl_token_2022::native_mint::DECIMALS,
+                is_initialized: true,
+                ..Default::default()
+            },
+        );
+    }
     let (mut banks_client, _payer_keypair, mut _recent_blockhash) = program.start().await;
 
-    let token_account = banks_client
-        .create_associated_token_account(&payer.pubkey(), &mint_pubkey, &payer, &token_program_id)
+    let lamports = 1_000_000_000;
+    let wrapped = banks_client
+        .wrap_sol(&token_program_id, &payer, lamports)
         .await
         .unwrap();
+    let token_account = banks_client.get_token_account(wrapped).await.unwrap();
+    assert_eq!(token_account.token_program_id, token_program_id);
+    assert!(token_account.base().is_native());
+    assert_eq!(token_account.base().owner, payer.pubkey());
+    assert_eq!(token_account.base().amount, lamports);
 
-    let token_account = banks_client
-        .get_account(token_account)
+    // wrapping again tops up the existing account
+    banks_client
+        .wrap_sol(&token_program_id, &payer, lamports)
+        .await
+        .unwrap();
+    let token_account = banks_client.get_token_account(wrapped).await.unwrap();
+    assert_eq!(token_account.base().amount, 2 * lamports);
+
+    let balance = banks_client.get_balance(payer.pubkey()).await.unwrap();
+    banks_client
+        .unwrap_sol(&token_program_id, &payer)
+        .await
+        .unwrap();
+    assert!(banks_client.get_account(wrapped).await.unwrap().is_none());
+    assert!(banks_client.get_balance(payer.pubkey()).await.unwrap() > balance + 2 * lamports);
+}
+
+#[tokio::test]
+async fn send_transactions() {
+    let (mut program, _) = helpers::add_program();
+    let payer = helpers::add_payer(&mut program);
+    let (mut banks_client, _payer_keypair, recent_blockhash) = program.start().await;
+    let transfer = |from: &Keypair, to: &Pubkey, lamports: u64| {
+        Transaction::new_signed_with_payer(
+            &[system_instruction::transfer(&from.pubkey(), to, lamports)],
+            Some(&payer.pubkey()),
+            &[&payer, from],
+            recent_blockhash,
+        )
+    };
+
+    // independent transfers, the outcomes are in the order of the batch
+    let recipients: Vec<Pubkey> = (0..20).map(|_| Pubkey::new_unique()).collect();
+    let transactions: Vec<Transaction> = recipients
+        .iter()
+        .map(|recipient| transfer(&payer, recipient, 1_000_000))
+        .collect();
+    let outcomes = banks_client
+        .send_transactions(transactions.clone(), SendMode::ConcurrentUnordered)
+        .await
+        .unwrap();
+    for (transaction, outcome) in transactions.iter().zip(outcomes) {
+        let outcome = outcome.unwrap();
+        assert_eq!(outcome.signature, transaction.signatures[0]);
+        assert!(outcome.result.is_ok());
+        assert!(outcome.compute_units_consumed > 0);
+    }
+    for recipient in &recipients {
+        assert_eq!(
+            banks_client.get_balance(*recipient).await.unwrap(),
+            1_000_000
+        );
+    }
+
+    let outcomes = banks_client
+        .send_transactions(
+            vec![
+                transfer(&payer, &recipients[0], 1),
+                transfer(&payer, &recipients[0], 2),
+            ],
+            SendMode::Sequential,
+        )
         .await
-        .unwrap()
         .unwrap();
+    assert!(outcomes
+        .iter()
+        .all(|outcome| outcome.as_ref().unwrap().result.is_ok()));
+    assert_eq!(
+        banks_client.get_balance(recipients[0]).await.unwrap(),
+        1_000_003
+    );
 
-    let token_account_data = TokenAccount::unpack(&token_account.data).unwrap();
+    // the second transfer spends what the first one sent, the last one depends on a failed transfer
+    let intermediary = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    let outcomes = banks_client
+        .send_transactions(
+            vec![
+                transfer(&payer, &intermediary.pubkey(), 2_000_000),
+                transfer(&intermediary, &recipient, 1_000_000),
+                transfer(&payer, &recipient, u64::MAX),
+                transfer(&payer, &recipient, 3),
+            ],
+            SendMode::ConcurrentWithDependencies(vec![vec![], vec![0], vec![], vec![1, 2]]),
+        )
+        .await
+        .unwrap();
+    assert!(outcomes[0].as_ref().unwrap().result.is_ok());
+    assert!(outcomes[1].as_ref().unwrap().result.is_ok());
+    assert!(outcomes[2].as_ref().unwrap().result.is_err());
+    assert!(!outcomes[2].as_ref().unwrap().logs.is_empty());
+    assert!(matches!(outcomes[3], Err(TestFrameWorkError::Error(_))));
+    assert_eq!(
+        banks_client.get_balance(recipient).await.unwrap(),
+        1_000_000
+    );
 
-    assert_eq!(token_account_data.mint, mint_pubkey);
-    assert_eq!(token_account_data.owner, payer.pubkey());
+    // transactions can only depend on transactions before them
+    assert!(matches!(
+        banks_client
+            .send_transactions(
+                vec![
+                    transfer(&payer, &recipient, 4),
+                    transfer(&payer, &recipient, 5)
+                ],
+                SendMode::ConcurrentWithDependencies(vec![vec![1], vec![]]),
+            )
+            .await,
+        Err(TestFrameWorkError::Error(_))
+    ));
 }
 
 #[tokio::test]
@@ -304,3 +1304,308 @@ async fn deploy_upgradable_program() {
         Pubkey::from_str("BPFLoaderUpgradeab1e11111111111111111111111").unwrap()
     );
 }
+
+#[tokio::test]
+async fn deploy_program_from_bytes() {
+    let program_data = std::fs::read("tests/artifacts/program_for_tests.so").unwrap();
+    let (mut program, _) = helpers::add_program();
+    let payer = helpers::add_payer(&mut program);
+    let (mut banks_client, _payer_keypair, _recent_blockhash) = program.start().await;
+
+    let program_keypair = Keypair::new();
+    banks_client
+        .deploy_upgradable_program_from_bytes(
+            &program_data,
+            &Keypair::new(),
+            &payer,
+            &program_keypair,
+            Some(program_data.len() + 1024),
+            &payer,
+        )
+        .await
+        .unwrap();
+    let info = banks_client
+        .get_program_info(&program_keypair.pubkey())
+        .await
+        .unwrap();
+    assert_eq!(info.data_len, program_data.len() + 1024);
+    assert_eq!(info.upgrade_authority, Some(payer.pubkey()));
+
+    let result = banks_client
+        .deploy_upgradable_program_from_bytes(
+            &program_data,
+            &Keypair::new(),
+            &payer,
+            &Keypair::new(),
+            Some(program_data.len() - 1),
+            &payer,
+        )
+        .await;
+    assert!(matches!(result, Err(TestFrameWorkError::InvalidProgram(_))));
+
+    let result = banks_client
+        .deploy_upgradable_program(
+            "tests/artifacts/missing.so",
+            &Keypair::new(),
+            &payer,
+            &Keypair::new(),
+            &payer,
+        )
+        .await;
+    assert!(matches!(result, Err(TestFrameWorkError::Io(_))));
+
+    let buffer_keypair = Keypair::new();
+    let result = banks_client
+        .deploy_upgradable_program_from_bytes(
+            &program_data[..64],
+            &buffer_keypair,
+            &payer,
+            &Keypair::new(),
+            None,
+            &payer,
+        )
+        .await;
+    assert!(matches!(result, Err(TestFrameWorkError::InvalidProgram(_))));
+    assert!(banks_client
+        .get_account(buffer_keypair.pubkey())
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn write_program_buffer() {
+    let program_data = std::fs::read("tests/artifacts/program_for_tests.so").unwrap();
+    let (mut program, _) = helpers::add_program();
+    let payer = helpers::add_payer(&mut program);
+    let (mut banks_client, _payer_keypair, _recent_blockhash) = program.start().await;
+
+    let buffer = Keypair::new();
+    banks_client
+        .write_program_buffer(&buffer, &payer, &program_data, &payer)
+        .await
+        .unwrap();
+
+    let buffer_account = banks_client
+        .get_account(buffer.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        &buffer_account.data[UpgradeableLoaderState::size_of_buffer_metadata()..],
+        &program_data[..]
+    );
+}
+
+#[tokio::test]
+async fn program_upgrade_lifecycle() {
+    let program_path = "tests/artifacts/program_for_tests.so";
+    let program_len = std::fs::metadata(program_path).unwrap().len() as usize;
+    let authority = Keypair::new();
+    let program_id = Pubkey::new_unique();
+    let closed_program_id = Pubkey::new_unique();
+
+    let mut program = ProgramTest::default();
+    let payer = helpers::add_payer(&mut program);
+    for program_id in [program_id, closed_program_id] {
+        program.add_bpf_program(
+            "tests/artifacts/program_for_tests",
+            program_id,
+            Some(authority.pubkey()),
+            None,
+        );
+    }
+    let mut context = program.start_with_context().await;
+
+    let info = context
+        .banks_client
+        .get_program_info(&program_id)
+        .await
+        .unwrap();
+    assert_eq!(info.upgrade_authority, Some(authority.pubkey()));
+    assert_eq!(info.data_len, program_len);
+
+    context
+        .banks_client
+        .extend_program(&program_id, 1024, &payer)
+        .await
+        .unwrap();
+    let info = context
+        .banks_client
+        .get_program_info(&program_id)
+        .await
+        .unwrap();
+    assert_eq!(info.data_len, program_len + 1024);
+
+    // a program cannot be upgraded in the slot it was deployed or extended in
+    context.warp_to_slot(5).unwrap();
+    context
+        .banks_client
+        .upgrade_program(
+            &program_id,
+            program_path,
+            &Keypair::new(),
+            &authority,
+            &payer,
+        )
+        .await
+        .unwrap();
+    let info = context
+        .banks_client
+        .get_program_info(&program_id)
+        .await
+        .unwrap();
+    assert_eq!(info.last_deploy_slot, 5);
+
+    let buffer = Keypair::new();
+    context
+        .banks_client
+        .write_program_buffer(&buffer, &authority, &[1, 2, 3], &payer)
+        .await
+        .unwrap();
+    context
+        .banks_client
+        .close_buffer(&buffer.pubkey(), &authority, &payer.pubkey(), &payer)
+        .await
+        .unwrap();
+    assert!(context
+        .banks_client
+        .get_account(buffer.pubkey())
+        .await
+        .unwrap()
+        .is_none());
+
+    let new_authority = Keypair::new();
+    context
+        .banks_client
+        .set_upgrade_authority(
+            &program_id,
+            &authority,
+            Some(&new_authority.pubkey()),
+            &payer,
+        )
+        .await
+        .unwrap();
+    let info = context
+        .banks_client
+        .get_program_info(&program_id)
+        .await
+        .unwrap();
+    assert_eq!(info.upgrade_authority, Some(new_authority.pubkey()));
+
+    context
+        .banks_client
+        .set_upgrade_authority(&program_id, &new_authority, None, &payer)
+        .await
+        .unwrap();
+    let info = context
+        .banks_client
+        .get_program_info(&program_id)
+        .await
+        .unwrap();
+    assert_eq!(info.upgrade_authority, None);
+
+    let programdata_address = context
+        .banks_client
+        .get_program_info(&closed_program_id)
+        .await
+        .unwrap()
+        .programdata_address;
+    context
+        .banks_client
+        .close_program(&closed_program_id, &authority, &payer.pubkey(), &payer)
+        .await
+        .unwrap();
+    assert!(context
+        .banks_client
+        .get_account(programdata_address)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(context
+        .banks_client
+        .get_program_info(&closed_program_id)
+        .await
+        .is_err());
+}
+
+// needs the loader-v4 profile: cargo test --profile loader-v4 --features loader-v4
+#[cfg(feature = "loader-v4")]
+#[tokio::test]
+async fn loader_v4_program_lifecycle() {
+    use solana_sdk::loader_v4::{LoaderV4State, DEPLOYMENT_COOLDOWN_IN_SLOTS};
+
+    let program_data = std::fs::read("tests/artifacts/sbpf_v2_program.so").unwrap();
+    let upgraded_program_data =
+        std::fs::read("tests/artifacts/sbpf_v2_program_upgrade.so").unwrap();
+    let authority = Keypair::new();
+    let seeded_program_id = Pubkey::new_unique();
+
+    let mut program = ProgramTest::default();
+    let payer = helpers::add_payer(&mut program);
+    program.add_loader_v4_program(
+        "tests/artifacts/sbpf_v2_program",
+        seeded_program_id,
+        authority.pubkey(),
+        LoaderV4Status::Deployed,
+    );
+    let mut context = program.start_with_context().await;
+
+    let program_keypair = Keypair::new();
+    context
+        .banks_client
+        .deploy_program_v4(&program_keypair, &authority, &program_data, &payer)
+        .await
+        .unwrap();
+    let state = context
+        .banks_client
+        .get_program_v4_state(&program_keypair.pubkey())
+        .await
+        .unwrap();
+    assert_eq!(state.status, LoaderV4Status::Deployed);
+    assert_eq!(state.authority_address, authority.pubkey());
+
+    // a program can only be retracted or redeployed after the deployment cooldown
+    let slot = DEPLOYMENT_COOLDOWN_IN_SLOTS + 2;
+    context.warp_to_slot(slot).unwrap();
+    context
+        .banks_client
+        .upgrade_program_v4(
+            &program_keypair.pubkey(),
+            &Keypair::new(),
+            &authority,
+            &upgraded_program_data,
+            &payer,
+        )
+        .await
+        .unwrap();
+    let state = context
+        .banks_client
+        .get_program_v4_state(&program_keypair.pubkey())
+        .await
+        .unwrap();
+    assert_eq!(state.status, LoaderV4Status::Deployed);
+    assert_eq!(state.slot, slot);
+    let program_account = context
+        .banks_client
+        .get_account(program_keypair.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        &program_account.data[LoaderV4State::program_data_offset()..],
+        &upgraded_program_data[..]
+    );
+
+    context
+        .banks_client
+        .retract_program_v4(&seeded_program_id, &authority, &payer)
+        .await
+        .unwrap();
+    let state = context
+        .banks_client
+        .get_program_v4_state(&seeded_program_id)
+        .await
+        .unwrap();
+    assert_eq!(state.status, LoaderV4Status::Retracted);
+}
@@ -1,9 +1,9 @@
 use solana_test_framework::*;
 
 use solana_sdk::{
-    account::Account,
     pubkey::Pubkey,
     signature::{Keypair, Signer},
+    system_program,
 };
 
 use std::str::FromStr;
@@ -19,15 +19,9 @@ pub fn add_program() -> (ProgramTest, Pubkey) {
     (program, program_id)
 }
 
-pub fn add_payer(program: &mut ProgramTest) -> Keypair {
+pub fn add_payer(program: &mut impl ProgramTestExtension) -> Keypair {
     let payer = Keypair::new();
-    program.add_account(
-        payer.pubkey(),
-        Account {
-            lamports: 1_000_000_000_000,
-            ..Account::default()
-        },
-    );
+    program.add_account_with_lamports(payer.pubkey(), system_program::id(), 1_000_000_000_000);
 
     return payer;
 }
@@ -0,0 +1,224 @@
+#![cfg(feature = "idl")]
+
+use solana_test_framework::{error::TestFrameWorkError, idl::AnchorIdl, *};
+
+use serde_json::json;
+use solana_sdk::{instruction::AccountMeta, pubkey::Pubkey, system_program};
+
+const IDL_PATH: &str = "tests/artifacts/vault_idl.json";
+
+#[test]
+fn build_instruction_from_idl() {
+    let idl = AnchorIdl::from_file(IDL_PATH).unwrap();
+    let program_id = idl.program_id();
+    let payer = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+
+    let instruction = idl
+        .instruction("initialize")
+        .unwrap()
+        .args(json!({
+            "params": {
+                "seed": 7,
+                "name": "main",
+                "fee_bps": "250",
+                "admins": [admin.to_string()],
+                "tag": [1, 2, 3, 4],
+            }
+        }))
+        .account("payer", payer)
+        .build()
+        .unwrap();
+
+    let mut data = vec![175, 175, 109, 31, 13, 152, 155, 237];
+    data.extend(7u64.to_le_bytes());
+    data.extend(4u32.to_le_bytes());
+    data.extend(b"main");
+    data.extend(250u16.to_le_bytes());
+    data.extend(1u32.to_le_bytes());
+    data.extend(admin.to_bytes());
+    data.extend([1, 2, 3, 4]);
+    assert_eq!(instruction.data, data);
+
+    let (config, _) = Pubkey::find_program_address(&[b"config"], &program_id);
+    let (vault, _) = Pubkey::find_program_address(
+        &[b"vault", payer.as_ref(), &7u64.to_le_bytes(), b"main"],
+        &program_id,
+    );
+    assert_eq!(instruction.program_id, program_id);
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new_readonly(config, false),
+            AccountMeta::new(vault, false),
+            // optional accounts that are not given are replaced by the program id
+            AccountMeta::new_readonly(program_id, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ]
+    );
+
+    // a different program id is used for the instruction and the PDAs
+    let other_program_id = Pubkey::new_unique();
+    let instruction = AnchorIdl::from_file(IDL_PATH)
+        .unwrap()
+        .with_program_id(other_program_id)
+        .instruction("initialize")
+        .unwrap()
+        .args(json!({
+            "params": { "seed": 7, "name": "main", "fee_bps": 250, "admins": [], "tag": [0, 0, 0, 0] }
+        }))
+        .account("payer", payer)
+        .build()
+        .unwrap();
+    let (config, _) = Pubkey::find_program_address(&[b"config"], &other_program_id);
+    assert_eq!(instruction.program_id, other_program_id);
+    assert_eq!(instruction.accounts[1].pubkey, config);
+}
+
+#[test]
+fn build_instruction_with_nested_accounts() {
+    let idl = AnchorIdl::from_file(IDL_PATH).unwrap();
+    let owner = Pubkey::new_unique();
+    let vault = Pubkey::new_unique();
+
+    let instruction = idl
+        .instruction("deposit")
+        .unwrap()
+        .arg("amount", json!(1_000))
+        .arg("memo", json!("rent"))
+        .arg("kind", json!({ "Locked": { "until": -1 } }))
+        .account("common.owner", owner)
+        .build()
+        .unwrap();
+
+    let mut data = vec![242, 35, 198, 137, 82, 225, 242, 182];
+    data.extend(1_000u64.to_le_bytes());
+    data.push(1);
+    data.extend(4u32.to_le_bytes());
+    data.extend(b"rent");
+    data.push(1);
+    data.extend((-1i64).to_le_bytes());
+    assert_eq!(instruction.data, data);
+
+    let (derived_vault, _) =
+        Pubkey::find_program_address(&[b"vault", owner.as_ref()], &idl.program_id());
+    assert_eq!(
+        instruction.accounts,
+        vec![
+            AccountMeta::new_readonly(owner, true),
+            AccountMeta::new(derived_vault, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ]
+    );
+
+    // accounts given explicitly take precedence over the PDA of the IDL
+    let instruction = idl
+        .instruction("deposit")
+        .unwrap()
+        .args(json!({ "amount": 1, "memo": null, "kind": "Basic" }))
+        .account("common.owner", owner)
+        .account("common.vault", vault)
+        .build()
+        .unwrap();
+    assert_eq!(instruction.accounts[1], AccountMeta::new(vault, false));
+    assert_eq!(&instruction.data[8..], &[1, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn build_instruction_from_idl_errors() {
+    let idl = AnchorIdl::from_file(IDL_PATH).unwrap();
+    let args = json!({ "amount": 1, "memo": null, "kind": "Basic" });
+
+    assert!(matches!(
+        idl.instruction("withdraw"),
+        Err(TestFrameWorkError::Idl(_))
+    ));
+    assert!(matches!(
+        AnchorIdl::from_file("tests/artifacts/missing_idl.json"),
+        Err(TestFrameWorkError::Io(_))
+    ));
+
+    let results = [
+        // the PDA of the vault cannot be derived without its owner
+        idl.instruction("deposit")
+            .unwrap()
+            .args(args.clone())
+            .build(),
+        idl.instruction("deposit")
+            .unwrap()
+            .args(args.clone())
+            .account("common.owner", Pubkey::new_unique())
+            .account("authority", Pubkey::new_unique())
+            .build(),
+        idl.instruction("deposit")
+            .unwrap()
+            .args(json!({ "amount": 1, "kind": "Basic" }))
+            .account("common.owner", Pubkey::new_unique())
+            .build(),
+        idl.instruction("deposit")
+            .unwrap()
+            .args(json!({ "amount": -1, "memo": null, "kind": "Basic" }))
+            .account("common.owner", Pubkey::new_unique())
+            .build(),
+        idl.instruction("deposit")
+            .unwrap()
+            .args(json!({ "amount": 1, "memo": null, "kind": "Frozen" }))
+            .account("common.owner", Pubkey::new_unique())
+            .build(),
+        // the arguments must be an object keyed by argument name
+        idl.instruction("deposit")
+            .unwrap()
+            .args(json!([1, null, "Basic"]))
+            .account("common.owner", Pubkey::new_unique())
+            .build(),
+    ];
+    for result in results {
+        assert!(
+            matches!(result, Err(TestFrameWorkError::Idl(_))),
+            "{:?}",
+            result
+        );
+    }
+}
+
+#[tokio::test]
+async fn account_as_json() {
+    let idl = AnchorIdl::from_file(IDL_PATH).unwrap();
+    let vault = Pubkey::new_unique();
+    let other_account = Pubkey::new_unique();
+    let value = json!({
+        "owner": Pubkey::new_unique().to_string(),
+        "balance": 1_000,
+        "total_deposits": "340282366920938463463374607431768211455",
+        "kind": { "Locked": { "until": 1_700_000_000 } },
+        "name": "main",
+        "unlock_at": null,
+        "history": [-5, 10],
+        "bump": 254,
+    });
+
+    let mut program = ProgramTest::default();
+    program.add_account_with_json(vault, &idl, "Vault", value.clone());
+    program.add_account_with_data(other_account, idl.program_id(), &[0; 16], false);
+    let (mut banks_client, _payer, _recent_blockhash) = program.start().await;
+
+    let account = banks_client.get_account(vault).await.unwrap().unwrap();
+    assert_eq!(account.owner, idl.program_id());
+    assert_eq!(&account.data[..8], &[211, 8, 232, 43, 2, 152, 117, 119]);
+    assert_eq!(idl.decode_account(&account.data).unwrap().0, "Vault");
+
+    let decoded = banks_client.get_account_as_json(vault, &idl).await.unwrap();
+    assert_eq!(decoded, value);
+
+    assert!(matches!(
+        banks_client.get_account_as_json(other_account, &idl).await,
+        Err(TestFrameWorkError::Idl(_))
+    ));
+    assert!(matches!(
+        banks_client
+            .get_account_as_json(Pubkey::new_unique(), &idl)
+            .await,
+        Err(TestFrameWorkError::AccountNotFound(_))
+    ));
+}
@@ -0,0 +1,244 @@
+use solana_test_framework::*;
+
+use {
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{
+        bpf_loader_upgradeable::UpgradeableLoaderState,
+        commitment_config::CommitmentConfig,
+        hash::Hash,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        system_instruction, system_program,
+        transaction::{Transaction, TransactionError},
+    },
+    solana_test_framework::error::TestFrameWorkError,
+};
+
+use solana_test_validator::TestValidatorGenesis;
+
+use std::str::FromStr;
+
+async fn start_validator() -> (RpcClient, Keypair, solana_test_validator::TestValidator) {
+    let (test_validator, payer) = TestValidatorGenesis::default().start_async().await;
+    let rpc_client = test_validator.get_async_rpc_client();
+
+    (rpc_client, payer, test_validator)
+}
+
+#[tokio::test]
+async fn transaction_from_instructions() {
+    let (mut rpc_client, payer, _test_validator) = start_validator().await;
+
+    let acc_1 = Keypair::new();
+    let acc_2 = Keypair::new();
+    let acc_lamports = 1_000_000;
+    let ix_1 = system_instruction::create_account(
+        &payer.pubkey(),
+        &acc_1.pubkey(),
+        acc_lamports,
+        1,
+        &acc_1.pubkey(),
+    );
+    let ix_2 = system_instruction::create_account(
+        &payer.pubkey(),
+        &acc_2.pubkey(),
+        acc_lamports,
+        1,
+        &acc_2.pubkey(),
+    );
+    let tx = rpc_client
+        .transaction_from_instructions(&[ix_1, ix_2], &payer, vec![&payer, &acc_1, &acc_2])
+        .await
+        .unwrap();
+
+    assert!(rpc_client.send_and_confirm_transaction(&tx).await.is_ok());
+    let acc1_data = rpc_client.get_account(&acc_1.pubkey()).await.unwrap();
+    let acc2_data = rpc_client.get_account(&acc_2.pubkey()).await.unwrap();
+    assert_eq!(acc1_data.owner, acc_1.pubkey());
+    assert_eq!(acc2_data.owner, acc_2.pubkey());
+}
+
+#[tokio::test]
+async fn deploy_upgradable_program() {
+    let (mut rpc_client, payer, _test_validator) = start_validator().await;
+
+    let program_keypair = Keypair::new();
+    let buffer_keypair = Keypair::new();
+    let buffer_authority_signer = Keypair::new();
+
+    rpc_client
+        .deploy_upgradable_program(
+            "tests/artifacts/program_for_tests.so",
+            &buffer_keypair,
+            &buffer_authority_signer,
+            &program_keypair,
+            &payer,
+        )
+        .await
+        .unwrap();
+    let deployed_program_account = rpc_client
+        .get_account(&program_keypair.pubkey())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        deployed_program_account.owner,
+        Pubkey::from_str("BPFLoaderUpgradeab1e11111111111111111111111").unwrap()
+    );
+}
+
+#[tokio::test]
+async fn write_program_buffer() {
+    let (mut rpc_client, payer, _test_validator) = start_validator().await;
+    let program_data = std::fs::read("tests/artifacts/program_for_tests.so").unwrap();
+
+    let buffer = Keypair::new();
+    rpc_client
+        .write_program_buffer(&buffer, &payer, &program_data, &payer)
+        .await
+        .unwrap();
+
+    let buffer_account = rpc_client.get_account(&buffer.pubkey()).await.unwrap();
+    assert_eq!(
+        &buffer_account.data[UpgradeableLoaderState::size_of_buffer_metadata()..],
+        &program_data[..]
+    );
+}
+
+#[tokio::test]
+async fn simulate_instructions() {
+    let (mut rpc_client, payer, _test_validator) = start_validator().await;
+    let recipient = Pubkey::new_unique();
+    let lamports = 10_000_000;
+
+    let simulation = rpc_client
+        .simulate_instructions(
+            &[system_instruction::transfer(
+                &payer.pubkey(),
+                &recipient,
+                lamports,
+            )],
+            &payer,
+            vec![&payer],
+            &[recipient],
+        )
+        .await
+        .unwrap();
+    assert!(simulation.result.is_ok());
+
+    let post_accounts = simulation.post_accounts.unwrap();
+    assert_eq!(post_accounts[0].as_ref().unwrap().lamports, lamports);
+    // nothing is committed
+    assert_eq!(rpc_client.get_balance(&recipient).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn get_program_accounts() {
+    let (mut rpc_client, payer, _test_validator) = start_validator().await;
+    let program_id = Pubkey::new_unique();
+    let small = Keypair::new();
+    let large = Keypair::new();
+    rpc_client
+        .create_account(&payer, &small, 10_000_000, 4, program_id)
+        .await
+        .unwrap();
+    rpc_client
+        .create_account(&payer, &large, 10_000_000, 8, program_id)
+        .await
+        .unwrap();
+
+    let all = rpc_client
+        .get_program_accounts(&program_id, vec![])
+        .await
+        .unwrap();
+    assert_eq!(all.len(), 2);
+
+    let filtered = rpc_client
+        .get_program_accounts(&program_id, vec![RpcFilterType::DataSize(4)])
+        .await
+        .unwrap();
+    assert_eq!(filtered.len(), 1);
+    assert_eq!(filtered[0].0, small.pubkey());
+}
+
+#[tokio::test]
+async fn rpc_send_policy() {
+    let (rpc_client, payer, _test_validator) = start_validator().await;
+    let mut rpc_client = RpcClientWithPolicy::new(rpc_client, RpcSendPolicy::new().max_retries(1));
+    let account = Keypair::new();
+
+    // confirm a single call at another commitment than the one of the client
+    let finalized = RpcSendPolicy::new().commitment(CommitmentConfig::finalized());
+    with_rpc_send_policy(
+        finalized,
+        rpc_client.create_account(&payer, &account, 1_000_000, 0, system_program::id()),
+    )
+    .await
+    .unwrap();
+    let finalized_account = rpc_client
+        .get_account_with_commitment(&account.pubkey(), CommitmentConfig::finalized())
+        .await
+        .unwrap()
+        .value;
+    assert!(finalized_account.is_some());
+
+    // transactions signed by the caller are sent again as is, so an unknown blockhash fails after the retries
+    let transaction = Transaction::new_signed_with_payer(
+        &[system_instruction::transfer(
+            &payer.pubkey(),
+            &account.pubkey(),
+            1,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer],
+        Hash::new_unique(),
+    );
+    let result = rpc_client
+        .process_versioned_transaction(transaction.into())
+        .await;
+    assert!(matches!(
+        result,
+        Err(TestFrameWorkError::Transaction {
+            error: TransactionError::BlockhashNotFound,
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn send_transactions() {
+    let (mut rpc_client, payer, _test_validator) = start_validator().await;
+    let recent_blockhash = rpc_client.get_latest_blockhash().await.unwrap();
+    let recipients: Vec<Pubkey> = (0..100).map(|_| Pubkey::new_unique()).collect();
+    let transactions: Vec<Transaction> = recipients
+        .iter()
+        .map(|recipient| {
+            Transaction::new_signed_with_payer(
+                &[system_instruction::transfer(
+                    &payer.pubkey(),
+                    recipient,
+                    1_000_000,
+                )],
+                Some(&payer.pubkey()),
+                &[&payer],
+                recent_blockhash,
+            )
+        })
+        .collect();
+
+    let outcomes = rpc_client
+        .send_transactions(transactions.clone(), SendMode::ConcurrentUnordered)
+        .await
+        .unwrap();
+    assert_eq!(outcomes.len(), transactions.len());
+    for (transaction, outcome) in transactions.iter().zip(outcomes) {
+        let outcome = outcome.unwrap();
+        assert_eq!(outcome.signature, transaction.signatures[0]);
+        assert!(outcome.result.is_ok());
+        assert!(!outcome.logs.is_empty());
+        assert!(outcome.compute_units_consumed > 0);
+    }
+    for recipient in &recipients {
+        assert_eq!(rpc_client.get_balance(recipient).await.unwrap(), 1_000_000);
+    }
+}
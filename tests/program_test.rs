@@ -5,7 +5,7 @@ use std::borrow::Borrow;
 use {
     solana_sdk::{
         native_token::sol_to_lamports, program_option::COption, program_pack::Pack, pubkey::Pubkey,
-        signature::Signer,
+        signature::Signer, sysvar::rent::Rent,
     },
     spl_token::state::{Account as TokenAccount, Mint},
 };
@@ -214,6 +214,48 @@ async fn add_token_account() {
     assert_eq!(token_account_data.owner, owner);
 }
 
+#[tokio::test]
+async fn add_native_token_account() {
+    let (mut program, _) = helpers::add_program();
+    let payer = helpers::add_payer(&mut program);
+
+    let token_account_pubkey = Pubkey::new_unique();
+    let amount = sol_to_lamports(2.0);
+    program.add_native_token_account(token_account_pubkey, payer.pubkey(), amount);
+
+    let (mut banks_client, _payer_keypair, mut _recent_blockhash) = program.start().await;
+    let token_account = banks_client
+        .get_account(token_account_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    let token_account_data = TokenAccount::unpack(&token_account.data).unwrap();
+    let rent_exempt_reserve = Rent::default().minimum_balance(TokenAccount::LEN);
+
+    assert_eq!(token_account_data.mint, spl_token::native_mint::id());
+    assert_eq!(token_account_data.amount, amount);
+    assert_eq!(
+        token_account_data.is_native,
+        COption::Some(rent_exempt_reserve)
+    );
+    assert_eq!(token_account.lamports, rent_exempt_reserve + amount);
+
+    // the account is consistent, so syncing it does not change the amount
+    banks_client
+        .sync_native(&spl_token::id(), &token_account_pubkey, &payer)
+        .await
+        .unwrap();
+    let token_account = banks_client
+        .get_account(token_account_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        TokenAccount::unpack(&token_account.data).unwrap().amount,
+        amount
+    );
+}
+
 #[tokio::test]
 async fn add_associated_token_account() {
     let (mut program, _) = helpers::add_program();
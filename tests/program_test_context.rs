@@ -1,8 +1,22 @@
-use solana_test_framework::*;
+use solana_test_framework::{error::TestFrameWorkError, *};
 
-use solana_sdk::{pubkey::Pubkey, sysvar::clock::Clock};
+use solana_sdk::{
+    account::AccountSharedData,
+    bpf_loader_upgradeable::UpgradeableLoaderState,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction,
+    sysvar::clock::Clock,
+    transaction::{Transaction, VersionedTransaction},
+};
+use spl_token::state::{Account as TokenAccount, Mint};
 
-use std::str::FromStr;
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 mod helpers;
 
@@ -37,6 +51,429 @@ async fn transaction_from_instructions() {
     assert_eq!(timestamp_before + moving_time, timestamp_now)
 }
 
+#[tokio::test]
+async fn advance_slots() {
+    let (program, _) = helpers::add_program();
+    let mut program_context = program.start_with_context().await;
+
+    let clock_before: Clock = program_context.banks_client.get_sysvar().await.unwrap();
+    // 25 slots of 400ms
+    program_context.advance_slots(25).await.unwrap();
+    let clock_after: Clock = program_context.banks_client.get_sysvar().await.unwrap();
+
+    assert_eq!(clock_after.slot, clock_before.slot + 25);
+    assert_eq!(clock_after.unix_timestamp, clock_before.unix_timestamp + 10);
+
+    program_context
+        .advance_slots_with_slot_duration(5, Duration::from_secs(2))
+        .await
+        .unwrap();
+    let clock_congested: Clock = program_context.banks_client.get_sysvar().await.unwrap();
+
+    assert_eq!(clock_congested.slot, clock_after.slot + 5);
+    assert_eq!(clock_congested.unix_timestamp, clock_after.unix_timestamp + 10);
+}
+
+#[tokio::test]
+async fn send() {
+    let (program, _) = helpers::add_program();
+    let recipient = Pubkey::new_unique();
+    let lamports = 1_000_000;
+
+    let mut program_context = program.start_with_context().await;
+    let ix = system_instruction::transfer(&program_context.payer.pubkey(), &recipient, lamports);
+
+    let first = program_context.send(&[ix.clone()], &[]).await.unwrap();
+    let second = program_context.send(&[ix.clone()], &[]).await.unwrap();
+    assert!(first.result.is_ok());
+    assert!(second.result.is_ok());
+    assert_ne!(first.signature, second.signature);
+    assert!(!first.logs.is_empty());
+    assert!(first.compute_units_consumed > 0);
+
+    let slot = program_context.banks_client.get_root_slot().await.unwrap();
+    program_context.warp_to_slot(slot + 1_000).unwrap();
+    let third = program_context.send(&[ix], &[]).await.unwrap();
+    assert!(third.result.is_ok());
+
+    let balance = program_context
+        .banks_client
+        .get_balance(recipient)
+        .await
+        .unwrap();
+    assert_eq!(balance, 3 * lamports);
+}
+
+#[tokio::test]
+async fn send_missing_signer() {
+    let (program, _) = helpers::add_program();
+    let new_acc = Keypair::new();
+
+    let mut program_context = program.start_with_context().await;
+    let ix = system_instruction::create_account(
+        &program_context.payer.pubkey(),
+        &new_acc.pubkey(),
+        1_000_000,
+        1,
+        &new_acc.pubkey(),
+    );
+
+    let result = program_context.send(&[ix], &[]).await;
+    assert!(matches!(result, Err(TestFrameWorkError::Signer(_))));
+}
+
+#[tokio::test]
+async fn tracked_accounts() {
+    let (program, _) = helpers::add_program();
+    let program_id = Pubkey::new_unique();
+    let mut program_context = TrackedProgramTest::from(program).start_with_context().await;
+
+    // accounts written with `set_account` are found
+    let written = Pubkey::new_unique();
+    program_context.set_account(&written, &AccountSharedData::new(1_000_000, 8, &program_id));
+
+    // so are accounts created by transactions sent directly through the client
+    let created = Keypair::new();
+    let ix = system_instruction::create_account(
+        &program_context.payer.pubkey(),
+        &created.pubkey(),
+        1_000_000,
+        8,
+        &program_id,
+    );
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&program_context.payer.pubkey()),
+        &[&program_context.payer, &created],
+        program_context.last_blockhash,
+    );
+    program_context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .unwrap();
+
+    let mut addresses: Vec<Pubkey> = program_context
+        .banks_client
+        .get_program_accounts(&program_id, vec![])
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(address, _)| address)
+        .collect();
+    addresses.sort();
+    let mut expected = vec![written, created.pubkey()];
+    expected.sort();
+    assert_eq!(addresses, expected);
+}
+
+#[tokio::test]
+async fn transaction_hooks() {
+    let (program, _) = helpers::add_program();
+    let mut program = TrackedProgramTest::from(program);
+    let payer = helpers::add_payer(&mut program);
+    let mut program_context = program.start_with_context().await;
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let seen_by_hook = seen.clone();
+    program_context.add_transaction_hook(
+        move |_: &VersionedTransaction, outcome: Option<&TransactionOutcome>| {
+            seen_by_hook
+                .lock()
+                .unwrap()
+                .push(outcome.map(|outcome| outcome.compute_units_consumed));
+        },
+    );
+
+    // setup transactions issued by ClientExtensions go through the hooks as well
+    program_context
+        .banks_client
+        .create_account(&payer, &Keypair::new(), 1_000_000, 0, payer.pubkey())
+        .await
+        .unwrap();
+    {
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert_eq!(seen[0], None);
+        assert!(seen[1].unwrap() > 0);
+    }
+
+    // hooks belong to their test, other tests on the same thread do not run them
+    let (other_program, _) = helpers::add_program();
+    let mut other_context = TrackedProgramTest::from(other_program)
+        .start_with_context()
+        .await;
+    let ix = system_instruction::transfer(
+        &other_context.payer.pubkey(),
+        &Pubkey::new_unique(),
+        1_000_000,
+    );
+    other_context.send(&[ix], &[]).await.unwrap();
+    assert_eq!(seen.lock().unwrap().len(), 2);
+
+    // clients of the test run them on other tasks as well
+    let mut banks_client = program_context.banks_client.clone();
+    let spawned_payer = payer.insecure_clone();
+    tokio::spawn(async move {
+        banks_client
+            .create_account(
+                &spawned_payer,
+                &Keypair::new(),
+                1_000_000,
+                0,
+                spawned_payer.pubkey(),
+            )
+            .await
+            .unwrap();
+    })
+    .await
+    .unwrap();
+    assert_eq!(seen.lock().unwrap().len(), 4);
+
+    // versioned transactions run them as well
+    let ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000_000);
+    let builder = TransactionBuilder::new(&[ix], &payer.pubkey());
+    let transaction = program_context
+        .banks_client
+        .build_versioned_transaction(&builder, &[&payer])
+        .await
+        .unwrap();
+    program_context
+        .banks_client
+        .process_versioned_transaction(transaction)
+        .await
+        .unwrap();
+    assert_eq!(seen.lock().unwrap().len(), 6);
+
+    program_context.clear_transaction_hooks();
+    let ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000_000);
+    program_context.send(&[ix], &[&payer]).await.unwrap();
+    assert_eq!(seen.lock().unwrap().len(), 6);
+}
+
+#[tokio::test]
+async fn transaction_hooks_in_batch() {
+    let (program, _) = helpers::add_program();
+    let mut program = TrackedProgramTest::from(program);
+    let payer = helpers::add_payer(&mut program);
+    let mut program_context = program.start_with_context().await;
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let seen_by_hook = seen.clone();
+    program_context.add_transaction_hook(
+        move |tx: &VersionedTransaction, outcome: Option<&TransactionOutcome>| {
+            seen_by_hook
+                .lock()
+                .unwrap()
+                .push((tx.signatures[0], outcome.is_some()));
+        },
+    );
+
+    let latest_blockhash = program_context.last_blockhash;
+    let transactions: Vec<Transaction> = (0..2)
+        .map(|_| {
+            let ix =
+                system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000_000);
+            Transaction::new_signed_with_payer(
+                &[ix],
+                Some(&payer.pubkey()),
+                &[&payer],
+                latest_blockhash,
+            )
+        })
+        .collect();
+    program_context
+        .banks_client
+        .send_transactions(transactions.clone(), SendMode::ConcurrentUnordered)
+        .await
+        .unwrap();
+
+    // the hooks of a transaction run right before and right after it
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec![
+            (transactions[0].signatures[0], false),
+            (transactions[0].signatures[0], true),
+            (transactions[1].signatures[0], false),
+            (transactions[1].signatures[0], true),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn take_over_program_upgrade_authority() {
+    let mut program = ProgramTest::default();
+    let program_id = Pubkey::from_str("CwrqeMj2U8tFr1Rhkgwc84tpAsqbt9pTt2a4taoTADPr").unwrap();
+    program.add_bpf_program(
+        "tests/artifacts/program_for_tests",
+        program_id,
+        Some(Pubkey::new_unique()),
+        None,
+    );
+    let new_authority = Pubkey::new_unique();
+
+    let mut program_context = program.start_with_context().await;
+    program_context
+        .take_over_program_upgrade_authority(program_id, new_authority)
+        .await
+        .unwrap();
+
+    let program_account = program_context
+        .banks_client
+        .get_account(program_id)
+        .await
+        .unwrap()
+        .unwrap();
+    let programdata_address = match bincode::deserialize(&program_account.data).unwrap() {
+        UpgradeableLoaderState::Program {
+            programdata_address,
+        } => programdata_address,
+        _ => panic!("not a program account"),
+    };
+    let program_data = program_context
+        .banks_client
+        .get_account(programdata_address)
+        .await
+        .unwrap()
+        .unwrap();
+
+    match bincode::deserialize(&program_data.data).unwrap() {
+        UpgradeableLoaderState::ProgramData {
+            upgrade_authority_address,
+            ..
+        } => assert_eq!(upgrade_authority_address, Some(new_authority)),
+        _ => panic!("not a ProgramData account"),
+    }
+}
+
+#[tokio::test]
+async fn take_over_token_authorities() {
+    let (mut program, _) = helpers::add_program();
+    let mint = Pubkey::new_unique();
+    let freezable_mint = Pubkey::new_unique();
+    let token_account = Pubkey::new_unique();
+    let new_authority = Pubkey::new_unique();
+
+    program.add_token_mint(mint, Some(Pubkey::new_unique()), 100, 0, None);
+    program.add_token_mint(
+        freezable_mint,
+        Some(Pubkey::new_unique()),
+        100,
+        0,
+        Some(Pubkey::new_unique()),
+    );
+    program.add_token_account(
+        token_account,
+        mint,
+        Pubkey::new_unique(),
+        100,
+        Some(Pubkey::new_unique()),
+        None,
+        10,
+        None,
+    );
+
+    let mut program_context = program.start_with_context().await;
+    program_context
+        .take_over_mint_authority(mint, new_authority)
+        .await
+        .unwrap();
+    program_context
+        .take_over_mint_authority(freezable_mint, new_authority)
+        .await
+        .unwrap();
+    program_context
+        .take_over_token_account(token_account, new_authority)
+        .await
+        .unwrap();
+
+    let mint_acc = program_context
+        .banks_client
+        .get_account(mint)
+        .await
+        .unwrap()
+        .unwrap();
+    let mint_data = Mint::unpack(&mint_acc.data).unwrap();
+    assert_eq!(mint_data.mint_authority.unwrap(), new_authority);
+    assert!(mint_data.freeze_authority.is_none());
+    assert_eq!(mint_data.supply, 100);
+
+    let freezable_mint_acc = program_context
+        .banks_client
+        .get_account(freezable_mint)
+        .await
+        .unwrap()
+        .unwrap();
+    let freezable_mint_data = Mint::unpack(&freezable_mint_acc.data).unwrap();
+    assert_eq!(freezable_mint_data.mint_authority.unwrap(), new_authority);
+    assert_eq!(freezable_mint_data.freeze_authority.unwrap(), new_authority);
+
+    let token_acc = program_context
+        .banks_client
+        .get_account(token_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let token_account_data = TokenAccount::unpack(&token_acc.data).unwrap();
+    assert_eq!(token_account_data.owner, new_authority);
+    assert_eq!(token_account_data.delegate.unwrap(), new_authority);
+    assert!(token_account_data.close_authority.is_none());
+}
+
+#[tokio::test]
+async fn take_over_authority_at_offset() {
+    let (mut program, program_id) = helpers::add_program();
+    let acc_pubkey = Pubkey::new_unique();
+    let new_authority = Pubkey::new_unique();
+    program.add_account_with_data(acc_pubkey, program_id, &[0u8; 40], false);
+
+    let mut program_context = program.start_with_context().await;
+    program_context
+        .take_over_authority_at_offset(acc_pubkey, 8, new_authority)
+        .await
+        .unwrap();
+    assert!(program_context
+        .take_over_authority_at_offset(acc_pubkey, 9, new_authority)
+        .await
+        .is_err());
+
+    let acc = program_context
+        .banks_client
+        .get_account(acc_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(&acc.data[8..40], new_authority.as_ref());
+}
+
+#[cfg(feature = "anchor")]
+#[tokio::test]
+async fn update_anchor_account() {
+    let (mut program, program_id) = helpers::add_program();
+    let acc_pubkey = Pubkey::new_unique();
+    program.add_account_with_anchor(
+        acc_pubkey,
+        program_id,
+        program_for_tests::CountTracker { count: 1 },
+        false,
+    );
+
+    let mut program_context = program.start_with_context().await;
+    program_context
+        .update_anchor_account(acc_pubkey, |tracker: &mut program_for_tests::CountTracker| {
+            tracker.count = 42
+        })
+        .await
+        .unwrap();
+
+    let tracker: program_for_tests::CountTracker = program_context
+        .banks_client
+        .get_account_with_anchor(acc_pubkey)
+        .await
+        .unwrap();
+    assert_eq!(tracker.count, 42);
+}
+
 #[cfg(feature = "pyth")]
 #[tokio::test]
 async fn update_pyth_oracle() {
@@ -124,3 +561,55 @@ async fn update_pyth_oracle() {
     let price_data = banks_client.get_pyth_price_account(oracle).await.unwrap();
     assert_eq!(price_data, price_account);
 }
+
+#[cfg(feature = "pyth")]
+#[tokio::test]
+async fn price_paths() {
+    let (program, program_id) = helpers::add_program();
+    let mut program = TrackedProgramTest::from(program);
+
+    let oracle = Pubkey::new_unique();
+    let price_info = PriceInfo {
+        price: 100,
+        conf: 1,
+        status: PriceStatus::Trading,
+        ..Default::default()
+    };
+
+    program
+        .add_pyth_oracle(oracle, program_id, None, Some(price_info), Some(0))
+        .unwrap();
+
+    let mut program_context = program.start_with_context().await;
+    let mut banks_client = program_context.banks_client.clone();
+
+    let clock: Clock = banks_client.get_sysvar().await.unwrap();
+    let crash_start = clock.unix_timestamp + 60;
+    let crash_end = crash_start + 60;
+
+    program_context.add_price_path(
+        oracle,
+        PythPricePath::from_points(vec![(crash_start, 80, 2), (crash_end, 20, 5)]),
+    );
+
+    program_context
+        .warp_to_timestamp(crash_start)
+        .await
+        .unwrap();
+    let price_data = banks_client.get_pyth_price_account(oracle).await.unwrap();
+    assert_eq!((price_data.agg.price, price_data.agg.conf), (80, 2));
+    assert_eq!(price_data.timestamp, crash_start);
+
+    program_context
+        .advance_slots_with_slot_duration(100, Duration::from_secs(1))
+        .await
+        .unwrap();
+    let price_data = banks_client.get_pyth_price_account(oracle).await.unwrap();
+    assert_eq!((price_data.agg.price, price_data.agg.conf), (20, 5));
+    assert_eq!(price_data.timestamp, crash_start + 100);
+
+    let clock: Clock = banks_client.get_sysvar().await.unwrap();
+    program_context.advance_slots(10).await.unwrap();
+    let price_data = banks_client.get_pyth_price_account(oracle).await.unwrap();
+    assert_eq!(price_data.valid_slot, clock.slot + 10);
+}
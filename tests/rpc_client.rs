@@ -113,6 +113,7 @@ async fn create_token_mint() {
             &payer.pubkey(),
             Some(&freeze_pubkey),
             decimals,
+            &spl_token::id(),
             &payer,
         )
         .await
@@ -151,6 +152,7 @@ async fn create_token_account() {
             &payer.pubkey(),
             Some(&freeze_pubkey),
             decimals,
+            &spl_token::id(),
             &payer,
         )
         .await
@@ -159,7 +161,13 @@ async fn create_token_account() {
     let token_account = Keypair::new();
 
     rpc_client
-        .create_token_account(&token_account, &payer.pubkey(), &mint.pubkey(), &payer)
+        .create_token_account(
+            &token_account,
+            &payer.pubkey(),
+            &mint.pubkey(),
+            &spl_token::id(),
+            &payer,
+        )
         .await
         .unwrap();
 
@@ -198,6 +206,7 @@ async fn create_associated_token_account() {
             &payer.pubkey(),
             Some(&freeze_pubkey),
             decimals,
+            &spl_token::id(),
             &payer,
         )
         .await